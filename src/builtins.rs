@@ -0,0 +1,457 @@
+use crate::environment::Environment;
+use crate::error::{self, RuntimeError};
+use crate::expr::{self, LiteralValue, Shared};
+use crate::function::NativeFunction;
+use crate::generator::Generator;
+use crate::map::{Map, Set};
+use crate::range::Range;
+use crate::scanner::Token;
+use std::{cell::RefCell, rc::Rc};
+
+type List = Rc<Shared<Vec<LiteralValue>>>;
+
+/// The iterator over a built-in collection, as returned by its `iter()` method.
+pub struct NativeIterator {
+    next: Box<dyn FnMut() -> Option<LiteralValue>>,
+}
+
+impl NativeIterator {
+    pub fn new(next: impl FnMut() -> Option<LiteralValue> + 'static) -> Self {
+        Self {
+            next: Box::new(next),
+        }
+    }
+
+    fn from_vec(items: Vec<LiteralValue>) -> Self {
+        let mut items = items.into_iter();
+        Self::new(move || items.next())
+    }
+}
+
+impl std::fmt::Debug for NativeIterator {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "<iterator>")
+    }
+}
+
+/// Resolves a negative or positive index against a collection of `len` items.
+pub fn resolve_index(index: &LiteralValue, len: usize) -> Result<usize, String> {
+    let index = match index {
+        LiteralValue::Number(x) if x.fract() == 0.0 => *x as i64,
+        _ => {
+            return Err(format!(
+                "Index must be an integer, got {}",
+                index.to_string()
+            ))
+        }
+    };
+
+    let resolved = if index < 0 { index + len as i64 } else { index };
+    if resolved < 0 || resolved >= len as i64 {
+        return Err(format!("Index {} out of range for length {}", index, len));
+    }
+
+    Ok(resolved as usize)
+}
+
+/// Resolves optional slice bounds, clamping them to the collection like Python does.
+pub fn resolve_slice(
+    start: Option<LiteralValue>,
+    end: Option<LiteralValue>,
+    len: usize,
+) -> Result<(usize, usize), String> {
+    let clamp = |bound: Option<LiteralValue>, default: usize| -> Result<usize, String> {
+        match bound {
+            None | Some(LiteralValue::Nil) => Ok(default),
+            Some(LiteralValue::Number(x)) if x.fract() == 0.0 => {
+                let x = x as i64;
+                let x = if x < 0 { x + len as i64 } else { x };
+                Ok(x.clamp(0, len as i64) as usize)
+            }
+            Some(other) => Err(format!(
+                "Slice bounds must be integers, got {}",
+                other.to_string()
+            )),
+        }
+    };
+
+    let start = clamp(start, 0)?;
+    let end = clamp(end, len)?;
+
+    Ok((start, end.max(start)))
+}
+
+fn method(
+    name: &str,
+    arity: usize,
+    fun: impl Fn(Vec<LiteralValue>) -> Result<LiteralValue, RuntimeError> + 'static,
+) -> LiteralValue {
+    LiteralValue::Native(Rc::new(NativeFunction::new(name, Some(arity), fun)))
+}
+
+/// Returns the iterator for `value`: built-in collections produce a native
+/// iterator, while instances provide one through an `iter()` method or act as
+/// their own iterator when they define `next()`.
+pub fn iter(value: &LiteralValue) -> Result<LiteralValue, RuntimeError> {
+    let iterator = match value {
+        LiteralValue::Iterator(_) | LiteralValue::Generator(_) => return Ok(value.clone()),
+        LiteralValue::List(items) => {
+            // Index-based so that items pushed during the loop are visited too.
+            let items = items.clone();
+            let mut i = 0;
+            NativeIterator::new(move || {
+                let item = items.borrow().get(i).cloned();
+                i += 1;
+                item
+            })
+        }
+        LiteralValue::Tuple(items) => NativeIterator::from_vec(items.as_ref().clone()),
+        LiteralValue::StringValue(s) => NativeIterator::from_vec(
+            s.chars()
+                .map(|c| LiteralValue::StringValue(c.to_string()))
+                .collect(),
+        ),
+        LiteralValue::Map(map) => NativeIterator::from_vec(
+            map.borrow()
+                .entries()
+                .iter()
+                .map(|(key, _)| key.clone())
+                .collect(),
+        ),
+        LiteralValue::Set(set) => NativeIterator::from_vec(set.borrow().items()),
+        LiteralValue::Range(range) => {
+            let range = range.clone();
+            let mut i = 0;
+            NativeIterator::new(move || {
+                if i < range.len() {
+                    i += 1;
+                    Some(LiteralValue::Number(range.get(i - 1)))
+                } else {
+                    None
+                }
+            })
+        }
+        LiteralValue::Instance(instance) => {
+            let class = instance.borrow().class.clone();
+            if let Some(method) = class.find_method("iter") {
+                let iterator = method.bind(instance.clone()).call(vec![])?;
+                return match &iterator {
+                    LiteralValue::Iterator(_)
+                    | LiteralValue::Generator(_)
+                    | LiteralValue::Instance(_) => Ok(iterator),
+                    _ => Err(RuntimeError::new(
+                        "TypeError",
+                        format!(
+                            "iter() must return an iterator, got {}",
+                            iterator.to_string()
+                        ),
+                    )),
+                };
+            }
+            if class.find_method("next").is_some() {
+                return Ok(value.clone());
+            }
+
+            return Err(RuntimeError::new(
+                "TypeError",
+                format!(
+                    "{} instance is not iterable, it has no iter() or next() method",
+                    class.name
+                ),
+            ));
+        }
+        _ => {
+            return Err(RuntimeError::new(
+                "TypeError",
+                format!("{} is not iterable", value.to_string()),
+            ))
+        }
+    };
+
+    Ok(LiteralValue::Iterator(Rc::new(RefCell::new(iterator))))
+}
+
+/// Advances an iterator returned by `iter`, yielding `None` once it is done.
+pub fn next(iterator: &LiteralValue) -> Result<Option<LiteralValue>, RuntimeError> {
+    match iterator {
+        LiteralValue::Iterator(iterator) => Ok((iterator.borrow_mut().next)()),
+        LiteralValue::Generator(generator) => Generator::resume(generator),
+        LiteralValue::Instance(instance) => {
+            let class = instance.borrow().class.clone();
+            let method = match class.find_method("next") {
+                Some(method) => method,
+                None => {
+                    return Err(RuntimeError::new(
+                        "TypeError",
+                        format!("{} instance has no next() method", class.name),
+                    ))
+                }
+            };
+
+            match method.bind(instance.clone()).call(vec![])? {
+                LiteralValue::Done => Ok(None),
+                value => Ok(Some(value)),
+            }
+        }
+        _ => Err(RuntimeError::new(
+            "TypeError",
+            format!("{} is not an iterator", iterator.to_string()),
+        )),
+    }
+}
+
+fn iter_method(value: LiteralValue) -> LiteralValue {
+    method("iter", 0, move |_| iter(&value))
+}
+
+pub fn iterator_method(
+    iterator: Rc<RefCell<NativeIterator>>,
+    name: &Token,
+) -> Result<LiteralValue, String> {
+    let value = match name.lexeme.as_str() {
+        "next" => method("next", 0, move |_| {
+            Ok((iterator.borrow_mut().next)().unwrap_or(LiteralValue::Done))
+        }),
+        "iter" => method("iter", 0, move |_| {
+            Ok(LiteralValue::Iterator(iterator.clone()))
+        }),
+        _ => return Err(format!("Undefined iterator method '{}'", name.lexeme)),
+    };
+
+    Ok(value)
+}
+
+pub fn generator_method(
+    generator: Rc<RefCell<Generator>>,
+    name: &Token,
+) -> Result<LiteralValue, String> {
+    let value = match name.lexeme.as_str() {
+        "next" => method("next", 0, move |_| {
+            Ok(Generator::resume(&generator)?.unwrap_or(LiteralValue::Done))
+        }),
+        "iter" => method("iter", 0, move |_| {
+            Ok(LiteralValue::Generator(generator.clone()))
+        }),
+        _ => return Err(format!("Undefined generator method '{}'", name.lexeme)),
+    };
+
+    Ok(value)
+}
+
+/// Makes a list, map, set or instance read-only. Other values are immutable
+/// anyway.
+pub fn freeze(value: &LiteralValue) {
+    match value {
+        LiteralValue::List(list) => list.freeze(),
+        LiteralValue::Map(map) => map.freeze(),
+        LiteralValue::Set(set) => set.freeze(),
+        LiteralValue::Instance(instance) => instance.freeze(),
+        _ => (),
+    }
+}
+
+pub fn is_frozen(value: &LiteralValue) -> bool {
+    match value {
+        LiteralValue::List(list) => list.is_frozen(),
+        LiteralValue::Map(map) => map.is_frozen(),
+        LiteralValue::Set(set) => set.is_frozen(),
+        LiteralValue::Instance(instance) => instance.is_frozen(),
+        _ => false,
+    }
+}
+
+/// Fails if `value` has been frozen, before it is mutated.
+pub fn check_mutable(value: &LiteralValue) -> Result<(), String> {
+    if !is_frozen(value) {
+        return Ok(());
+    }
+
+    let kind = match value {
+        LiteralValue::List(_) => "list".to_string(),
+        LiteralValue::Map(_) => "map".to_string(),
+        LiteralValue::Set(_) => "set".to_string(),
+        LiteralValue::Instance(instance) => format!("{} instance", instance.borrow().class.name),
+        _ => value.to_string(),
+    };
+    Err(format!("Cannot modify a frozen {}", kind))
+}
+
+pub fn define_globals(environment: &mut Environment) {
+    environment.define("done".to_string(), LiteralValue::Done);
+    environment.define(
+        "set".to_string(),
+        LiteralValue::Native(Rc::new(NativeFunction::new("set", None, |args| {
+            // A single list argument is expanded, since lists can't be set elements.
+            let items = match args.as_slice() {
+                [LiteralValue::List(items)] => items.borrow().clone(),
+                _ => args,
+            };
+            Ok(LiteralValue::Set(Rc::new(Shared::new(Set::from_items(
+                items,
+            )?))))
+        }))),
+    );
+    environment.define(
+        "freeze".to_string(),
+        LiteralValue::Native(Rc::new(NativeFunction::new("freeze", Some(1), |args| {
+            freeze(&args[0]);
+            Ok(args[0].clone())
+        }))),
+    );
+    environment.define(
+        "is_frozen".to_string(),
+        LiteralValue::Native(Rc::new(NativeFunction::new("is_frozen", Some(1), |args| {
+            Ok(LiteralValue::from_bool(is_frozen(&args[0])))
+        }))),
+    );
+}
+
+/// Wraps a method that mutates `value`, so that it fails once `value` is frozen.
+fn mutating_method(
+    value: LiteralValue,
+    name: &str,
+    arity: usize,
+    fun: impl Fn(Vec<LiteralValue>) -> Result<LiteralValue, RuntimeError> + 'static,
+) -> LiteralValue {
+    method(name, arity, move |args| {
+        check_mutable(&value).map_err(error::kind("TypeError"))?;
+        fun(args)
+    })
+}
+
+pub fn list_method(list: List, name: &Token) -> Result<LiteralValue, String> {
+    let value = match name.lexeme.as_str() {
+        "iter" => iter_method(LiteralValue::List(list.clone())),
+        "push" => mutating_method(LiteralValue::List(list.clone()), "push", 1, move |args| {
+            list.borrow_mut().extend(args);
+            Ok(LiteralValue::Nil)
+        }),
+        "pop" => mutating_method(
+            LiteralValue::List(list.clone()),
+            "pop",
+            0,
+            move |_| match list.borrow_mut().pop() {
+                Some(value) => Ok(value),
+                None => Err(RuntimeError::new(
+                    "IndexError",
+                    "Cannot pop from an empty list".to_string(),
+                )),
+            },
+        ),
+        "insert" => mutating_method(
+            LiteralValue::List(list.clone()),
+            "insert",
+            2,
+            move |mut args| {
+                let value = args.pop().unwrap();
+                let len = list.borrow().len();
+                // Inserting at the end is allowed. Other indices, negative ones
+                // included, resolve as for reads, so -1 inserts before the last.
+                let index = match &args[0] {
+                    LiteralValue::Number(x) if *x == len as f32 => len,
+                    index => resolve_index(index, len).map_err(error::kind("IndexError"))?,
+                };
+                list.borrow_mut().insert(index, value);
+                Ok(LiteralValue::Nil)
+            },
+        ),
+        "len" => method("len", 0, move |_| {
+            Ok(LiteralValue::Number(list.borrow().len() as f32))
+        }),
+        "contains" => method("contains", 1, move |args| {
+            // Copied out first, since `__eq__` may modify the list.
+            let items = list.borrow().clone();
+            for item in &items {
+                if expr::values_equal(item, &args[0])? {
+                    return Ok(LiteralValue::True);
+                }
+            }
+            Ok(LiteralValue::False)
+        }),
+        "reverse" => mutating_method(LiteralValue::List(list.clone()), "reverse", 0, move |_| {
+            list.borrow_mut().reverse();
+            Ok(LiteralValue::Nil)
+        }),
+        _ => return Err(format!("Undefined list method '{}'", name.lexeme)),
+    };
+
+    Ok(value)
+}
+
+pub fn map_method(map: Rc<Shared<Map>>, name: &Token) -> Result<LiteralValue, String> {
+    let value = match name.lexeme.as_str() {
+        "iter" => iter_method(LiteralValue::Map(map.clone())),
+        "keys" => method("keys", 0, move |_| {
+            let keys = map
+                .borrow()
+                .entries()
+                .iter()
+                .map(|(key, _)| key.clone())
+                .collect();
+            Ok(LiteralValue::List(Rc::new(Shared::new(keys))))
+        }),
+        "values" => method("values", 0, move |_| {
+            let values = map
+                .borrow()
+                .entries()
+                .iter()
+                .map(|(_, value)| value.clone())
+                .collect();
+            Ok(LiteralValue::List(Rc::new(Shared::new(values))))
+        }),
+        "has" => method("has", 1, move |args| {
+            Ok(LiteralValue::from_bool(
+                map.borrow().get(&args[0])?.is_some(),
+            ))
+        }),
+        "remove" => mutating_method(LiteralValue::Map(map.clone()), "remove", 1, move |args| {
+            Ok(map
+                .borrow_mut()
+                .remove(&args[0])?
+                .unwrap_or(LiteralValue::Nil))
+        }),
+        "len" => method("len", 0, move |_| {
+            Ok(LiteralValue::Number(map.borrow().len() as f32))
+        }),
+        _ => return Err(format!("Undefined map method '{}'", name.lexeme)),
+    };
+
+    Ok(value)
+}
+
+pub fn set_method(set: Rc<Shared<Set>>, name: &Token) -> Result<LiteralValue, String> {
+    let value = match name.lexeme.as_str() {
+        "iter" => iter_method(LiteralValue::Set(set.clone())),
+        "add" => mutating_method(LiteralValue::Set(set.clone()), "add", 1, move |mut args| {
+            set.borrow_mut().insert(args.pop().unwrap())?;
+            Ok(LiteralValue::Nil)
+        }),
+        "remove" => mutating_method(LiteralValue::Set(set.clone()), "remove", 1, move |args| {
+            Ok(LiteralValue::from_bool(set.borrow_mut().remove(&args[0])?))
+        }),
+        "contains" => method("contains", 1, move |args| {
+            Ok(LiteralValue::from_bool(set.borrow().contains(&args[0])?))
+        }),
+        "len" => method("len", 0, move |_| {
+            Ok(LiteralValue::Number(set.borrow().len() as f32))
+        }),
+        _ => return Err(format!("Undefined set method '{}'", name.lexeme)),
+    };
+
+    Ok(value)
+}
+
+pub fn range_method(range: Range, name: &Token) -> Result<LiteralValue, String> {
+    let value = match name.lexeme.as_str() {
+        "iter" => iter_method(LiteralValue::Range(range)),
+        "len" => method("len", 0, move |_| {
+            Ok(LiteralValue::Number(range.len() as f32))
+        }),
+        "contains" => method("contains", 1, move |args| {
+            Ok(LiteralValue::from_bool(range.contains(&args[0])))
+        }),
+        _ => return Err(format!("Undefined range method '{}'", name.lexeme)),
+    };
+
+    Ok(value)
+}
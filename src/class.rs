@@ -0,0 +1,167 @@
+use crate::error::RuntimeError;
+use crate::expr::{LiteralValue, Shared};
+use crate::function::Function;
+use crate::scanner::Token;
+use crate::traits::Trait;
+use std::{collections::HashMap, rc::Rc};
+
+pub struct Class {
+    pub name: String,
+    pub methods: HashMap<String, Rc<Function>>,
+    /// Methods called on the class itself, with `this` bound to the class.
+    pub statics: HashMap<String, Rc<Function>>,
+    /// Accessors run when a property is read as `instance.name`, or assigned.
+    pub getters: HashMap<String, Rc<Function>>,
+    pub setters: HashMap<String, Rc<Function>>,
+    pub traits: Vec<Rc<Trait>>,
+}
+
+impl Class {
+    /// Looks up `Class.name`, a static method bound to `class`.
+    pub fn get(class: &Rc<Class>, name: &Token) -> Result<LiteralValue, RuntimeError> {
+        match class.statics.get(&name.lexeme) {
+            Some(method) => Ok(LiteralValue::Function(Rc::new(
+                method.bind_this(LiteralValue::Class(class.clone())),
+            ))),
+            None => Err(RuntimeError::new(
+                "PropertyError",
+                format!(
+                    "Undefined static method '{}' for {}",
+                    name.lexeme, class.name
+                ),
+            )
+            .at(name)),
+        }
+    }
+
+    pub fn implements(self: &Self, implemented: &Rc<Trait>) -> bool {
+        self.traits
+            .iter()
+            .any(|other| Rc::ptr_eq(other, implemented))
+    }
+
+    pub fn find_method(self: &Self, name: &str) -> Option<Rc<Function>> {
+        self.methods.get(name).cloned()
+    }
+
+    /// Creates an instance, passing the arguments on to `init`.
+    pub fn call(
+        class: Rc<Class>,
+        arguments: Vec<LiteralValue>,
+        named: Vec<(Token, LiteralValue)>,
+        paren: &Token,
+    ) -> Result<LiteralValue, RuntimeError> {
+        let instance = Rc::new(Shared::new(Instance {
+            class: class.clone(),
+            fields: HashMap::new(),
+        }));
+
+        match class.find_method("init") {
+            Some(initializer) => {
+                initializer
+                    .bind(instance.clone())
+                    .call_with(arguments, named, paren)?;
+            }
+            None if !named.is_empty() => {
+                return Err(RuntimeError::new(
+                    "ArgumentError",
+                    format!(
+                        "Unknown argument '{}' for {}",
+                        named[0].0.lexeme, class.name
+                    ),
+                )
+                .at(&named[0].0))
+            }
+            None if !arguments.is_empty() => {
+                return Err(RuntimeError::new(
+                    "ArgumentError",
+                    format!("Expected 0 arguments but got {}", arguments.len()),
+                )
+                .at(paren))
+            }
+            None => (),
+        }
+
+        Ok(LiteralValue::Instance(instance))
+    }
+}
+
+impl std::fmt::Debug for Class {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "<class {}>", self.name)
+    }
+}
+
+pub struct Instance {
+    pub class: Rc<Class>,
+    pub fields: HashMap<String, LiteralValue>,
+}
+
+impl Instance {
+    /// Looks up a field, falling back to the value of a getter and then to
+    /// a method bound to `instance`.
+    pub fn get(
+        instance: &Rc<Shared<Instance>>,
+        name: &Token,
+    ) -> Result<LiteralValue, RuntimeError> {
+        if let Some(value) = instance.borrow().fields.get(&name.lexeme) {
+            return Ok(value.clone());
+        }
+
+        let class = instance.borrow().class.clone();
+        if let Some(getter) = class.getters.get(&name.lexeme) {
+            return getter
+                .bind(instance.clone())
+                .call(vec![])
+                .map_err(|error| error.at(name));
+        }
+        match class.find_method(&name.lexeme) {
+            Some(method) => Ok(LiteralValue::Function(Rc::new(
+                method.bind(instance.clone()),
+            ))),
+            None => Err(RuntimeError::new(
+                "PropertyError",
+                format!("Undefined property '{}'", name.lexeme),
+            )
+            .at(name)),
+        }
+    }
+
+    /// Sets a field, or passes the value to a setter if the class has one.
+    pub fn set(
+        instance: &Rc<Shared<Instance>>,
+        name: &Token,
+        value: LiteralValue,
+    ) -> Result<(), RuntimeError> {
+        let class = instance.borrow().class.clone();
+        if let Some(setter) = class.setters.get(&name.lexeme) {
+            setter
+                .bind(instance.clone())
+                .call(vec![value])
+                .map_err(|error| error.at(name))?;
+            return Ok(());
+        }
+        if class.getters.contains_key(&name.lexeme) {
+            return Err(RuntimeError::new(
+                "PropertyError",
+                format!(
+                    "Property '{}' of {} has a getter but no setter",
+                    name.lexeme, class.name
+                ),
+            )
+            .at(name));
+        }
+
+        instance
+            .borrow_mut()
+            .fields
+            .insert(name.lexeme.clone(), value);
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for Instance {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "<{} instance>", self.class.name)
+    }
+}
@@ -0,0 +1,195 @@
+use crate::error::RuntimeError;
+use crate::expr::LiteralValue;
+use crate::function::NativeFunction;
+use crate::scanner::Token;
+use std::rc::Rc;
+
+/// An `enum` declaration: a type whose values are one of its variants.
+pub struct Enum {
+    pub name: String,
+    pub variants: Vec<Variant>,
+}
+
+/// A variant and the names of the fields its values carry, if any.
+pub struct Variant {
+    pub name: String,
+    pub fields: Vec<String>,
+}
+
+impl Enum {
+    /// Looks up `Enum.Variant`: the value itself for a variant without
+    /// fields, or else its constructor.
+    pub fn get(enum_type: &Rc<Enum>, name: &Token) -> Result<LiteralValue, String> {
+        match enum_type
+            .variants
+            .iter()
+            .position(|variant| variant.name == name.lexeme)
+        {
+            Some(index) if enum_type.variants[index].fields.is_empty() => {
+                Ok(LiteralValue::EnumValue(Rc::new(EnumValue {
+                    enum_type: enum_type.clone(),
+                    variant: index,
+                    values: vec![],
+                })))
+            }
+            Some(index) => Ok(LiteralValue::Variant(enum_type.clone(), index)),
+            None => Err(format!(
+                "Undefined variant '{}' for {}",
+                name.lexeme, enum_type.name
+            )),
+        }
+    }
+
+    /// Creates a value of the variant at `index` from positional and
+    /// `field: value` arguments, which must fill every field exactly once.
+    pub fn construct(
+        enum_type: Rc<Enum>,
+        index: usize,
+        arguments: Vec<LiteralValue>,
+        named: Vec<(Token, LiteralValue)>,
+        paren: &Token,
+    ) -> Result<LiteralValue, RuntimeError> {
+        let argument_error =
+            |message: String, token: &Token| RuntimeError::new("ArgumentError", message).at(token);
+        let variant = &enum_type.variants[index];
+
+        if arguments.len() > variant.fields.len() {
+            return Err(argument_error(
+                format!(
+                    "Expected {} arguments but got {}",
+                    variant.fields.len(),
+                    arguments.len()
+                ),
+                paren,
+            ));
+        }
+
+        let mut values: Vec<Option<LiteralValue>> = vec![None; variant.fields.len()];
+        for (i, argument) in arguments.into_iter().enumerate() {
+            values[i] = Some(argument);
+        }
+        for (name, value) in named {
+            match variant
+                .fields
+                .iter()
+                .position(|field| *field == name.lexeme)
+            {
+                Some(i) if values[i].is_none() => values[i] = Some(value),
+                Some(_) => {
+                    return Err(argument_error(
+                        format!("Got multiple values for argument '{}'", name.lexeme),
+                        &name,
+                    ))
+                }
+                None => {
+                    return Err(argument_error(
+                        format!("Unknown argument '{}' for {}", name.lexeme, variant.name),
+                        &name,
+                    ))
+                }
+            }
+        }
+
+        let values = values
+            .into_iter()
+            .zip(&variant.fields)
+            .map(|(value, field)| {
+                value.ok_or_else(|| {
+                    argument_error(
+                        format!("Missing argument '{}' for {}", field, variant.name),
+                        paren,
+                    )
+                })
+            })
+            .collect::<Result<Vec<LiteralValue>, RuntimeError>>()?;
+
+        Ok(LiteralValue::EnumValue(Rc::new(EnumValue {
+            enum_type: enum_type.clone(),
+            variant: index,
+            values,
+        })))
+    }
+}
+
+impl std::fmt::Debug for Enum {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "<enum {}>", self.name)
+    }
+}
+
+/// A value of one variant of an enum, with the values of its fields.
+pub struct EnumValue {
+    pub enum_type: Rc<Enum>,
+    pub variant: usize,
+    pub values: Vec<LiteralValue>,
+}
+
+impl EnumValue {
+    pub fn variant(self: &Self) -> &Variant {
+        &self.enum_type.variants[self.variant]
+    }
+
+    /// Whether `self` and `other` are the same variant of the same enum.
+    pub fn same_variant(self: &Self, enum_type: &Rc<Enum>, variant: usize) -> bool {
+        Rc::ptr_eq(&self.enum_type, enum_type) && self.variant == variant
+    }
+
+    pub fn equals(self: &Self, other: &EnumValue) -> bool {
+        other.same_variant(&self.enum_type, self.variant)
+            && self
+                .values
+                .iter()
+                .zip(&other.values)
+                .all(|(x, y)| x.equals(y))
+    }
+
+    /// Looks up a field by name, or the `to_string` method.
+    pub fn get(value: &Rc<EnumValue>, name: &Token) -> Result<LiteralValue, String> {
+        let variant = value.variant();
+        if let Some(i) = variant
+            .fields
+            .iter()
+            .position(|field| *field == name.lexeme)
+        {
+            return Ok(value.values[i].clone());
+        }
+
+        match name.lexeme.as_str() {
+            "to_string" => {
+                let value = value.clone();
+                Ok(LiteralValue::Native(Rc::new(NativeFunction::new(
+                    "to_string",
+                    Some(0),
+                    move |_| Ok(LiteralValue::StringValue(value.to_string())),
+                ))))
+            }
+            _ => Err(format!(
+                "Undefined field '{}' for {}.{}",
+                name.lexeme, value.enum_type.name, variant.name
+            )),
+        }
+    }
+
+    pub fn to_string(self: &Self) -> String {
+        let name = format!("{}.{}", self.enum_type.name, self.variant().name);
+        if self.values.is_empty() {
+            return name;
+        }
+
+        format!(
+            "{}({})",
+            name,
+            self.values
+                .iter()
+                .map(|value| value.to_repr())
+                .collect::<Vec<String>>()
+                .join(", ")
+        )
+    }
+}
+
+impl std::fmt::Debug for EnumValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.to_string())
+    }
+}
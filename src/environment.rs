@@ -0,0 +1,76 @@
+use crate::expr::LiteralValue;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+pub struct Environment {
+    values: HashMap<String, LiteralValue>,
+    /// The names in `values` declared with `const`.
+    constants: HashSet<String>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+/// Why `assign` failed.
+pub enum AssignError {
+    Undefined,
+    Constant,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Self {
+            values: HashMap::new(),
+            constants: HashSet::new(),
+            enclosing: None,
+        }
+    }
+
+    pub fn with_enclosing(enclosing: Rc<RefCell<Environment>>) -> Self {
+        Self {
+            values: HashMap::new(),
+            constants: HashSet::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    pub fn define(self: &mut Self, name: String, value: LiteralValue) {
+        self.constants.remove(&name);
+        self.values.insert(name, value);
+    }
+
+    /// Defines a variable that `assign` refuses to change.
+    pub fn define_constant(self: &mut Self, name: String, value: LiteralValue) {
+        self.constants.insert(name.clone());
+        self.values.insert(name, value);
+    }
+
+    /// Whether `name` is a constant defined in this scope itself.
+    pub fn is_constant(self: &Self, name: &str) -> bool {
+        self.constants.contains(name)
+    }
+
+    pub fn get(self: &Self, name: &str) -> Option<LiteralValue> {
+        match (self.values.get(name), &self.enclosing) {
+            (Some(value), _) => Some(value.clone()),
+            (None, Some(enclosing)) => enclosing.borrow().get(name),
+            (None, None) => None,
+        }
+    }
+
+    pub fn assign(self: &mut Self, name: &str, value: LiteralValue) -> Result<(), AssignError> {
+        if self.constants.contains(name) {
+            return Err(AssignError::Constant);
+        }
+        if let Some(old_value) = self.values.get_mut(name) {
+            *old_value = value;
+            return Ok(());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => Err(AssignError::Undefined),
+        }
+    }
+}
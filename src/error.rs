@@ -0,0 +1,133 @@
+use crate::class::{Class, Instance};
+use crate::expr::{LiteralValue, Shared};
+use crate::scanner::Token;
+use std::{collections::HashMap, rc::Rc};
+
+/// Why evaluation stopped early: an error raised by the interpreter, or a
+/// value thrown by a `throw` statement. Both unwind to the nearest `catch`.
+#[derive(Debug)]
+pub enum RuntimeError {
+    Error {
+        kind: String,
+        message: String,
+        line: Option<usize>,
+    },
+    Thrown {
+        value: LiteralValue,
+        line: usize,
+        /// The module the value was thrown in, once it escapes an import.
+        module: Option<String>,
+    },
+}
+
+impl RuntimeError {
+    /// An interpreter error, without a line until `at` gives it one.
+    pub fn new(kind: &str, message: String) -> Self {
+        RuntimeError::Error {
+            kind: kind.to_string(),
+            message,
+            line: None,
+        }
+    }
+
+    /// Fills in the line of `token` for errors raised without one.
+    pub fn at(self: Self, token: &Token) -> Self {
+        match self {
+            RuntimeError::Error {
+                kind,
+                message,
+                line: None,
+            } => RuntimeError::Error {
+                kind,
+                message,
+                line: Some(token.line_number),
+            },
+            error => error,
+        }
+    }
+
+    pub fn to_string(self: &Self) -> String {
+        match self {
+            RuntimeError::Error {
+                kind: _,
+                message,
+                line: Some(line),
+            } => format!("{} at line {}", message, line),
+            RuntimeError::Error {
+                kind: _,
+                message,
+                line: None,
+            } => message.clone(),
+            RuntimeError::Thrown {
+                value,
+                line,
+                module: None,
+            } => format!("Uncaught {} at line {}", value.to_repr(), line),
+            RuntimeError::Thrown {
+                value,
+                line,
+                module: Some(module),
+            } => format!(
+                "Uncaught {} at line {} in module '{}'",
+                value.to_repr(),
+                line,
+                module
+            ),
+        }
+    }
+
+    /// The value a `catch` clause receives: the thrown value itself, or an
+    /// `Error` instance with `message`, `kind` and `line` fields.
+    pub fn into_value(self: Self) -> LiteralValue {
+        match self {
+            RuntimeError::Thrown {
+                value,
+                line: _,
+                module: _,
+            } => value,
+            RuntimeError::Error {
+                kind,
+                message,
+                line,
+            } => {
+                let class = Rc::new(Class {
+                    name: "Error".to_string(),
+                    methods: HashMap::new(),
+                    statics: HashMap::new(),
+                    getters: HashMap::new(),
+                    setters: HashMap::new(),
+                    traits: vec![],
+                });
+                let fields = HashMap::from([
+                    ("message".to_string(), LiteralValue::StringValue(message)),
+                    ("kind".to_string(), LiteralValue::StringValue(kind)),
+                    (
+                        "line".to_string(),
+                        match line {
+                            Some(line) => LiteralValue::Number(line as f32),
+                            None => LiteralValue::Nil,
+                        },
+                    ),
+                ]);
+                LiteralValue::Instance(Rc::new(Shared::new(Instance { class, fields })))
+            }
+        }
+    }
+}
+
+impl From<String> for RuntimeError {
+    fn from(message: String) -> Self {
+        RuntimeError::new("Error", message)
+    }
+}
+
+impl From<RuntimeError> for String {
+    fn from(error: RuntimeError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Converts a `String` error into an error of `kind`, for use with `map_err`.
+pub fn kind(kind: &'static str) -> impl Fn(String) -> RuntimeError {
+    move |message| RuntimeError::new(kind, message)
+}
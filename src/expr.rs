@@ -1,4 +1,59 @@
+use crate::builtins::{self, NativeIterator};
+use crate::class::{Class, Instance};
+use crate::enums;
+use crate::environment::{AssignError, Environment};
+use crate::error::{self, RuntimeError};
+use crate::function::{self, Function, NativeFunction};
+use crate::generator::Generator;
+use crate::map::{Map, Set};
+use crate::module;
+use crate::pattern::MatchArm;
+use crate::range;
 use crate::scanner::{self, Token, TokenType};
+use crate::stmt::{Param, Stmt};
+use crate::traits::Trait;
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+/// A list, map, set or instance, shared by reference between everything
+/// that holds it. `freeze` makes it read-only.
+pub struct Shared<T> {
+    value: RefCell<T>,
+    frozen: Cell<bool>,
+}
+
+impl<T> Shared<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            value: RefCell::new(value),
+            frozen: Cell::new(false),
+        }
+    }
+
+    pub fn freeze(self: &Self) {
+        self.frozen.set(true);
+    }
+
+    pub fn is_frozen(self: &Self) -> bool {
+        self.frozen.get()
+    }
+}
+
+impl<T> std::ops::Deref for Shared<T> {
+    type Target = RefCell<T>;
+
+    fn deref(self: &Self) -> &RefCell<T> {
+        &self.value
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for Shared<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.value.fmt(f)
+    }
+}
 
 #[derive(Debug, Clone)]
 pub enum LiteralValue {
@@ -7,6 +62,24 @@ pub enum LiteralValue {
     True,
     False,
     Nil,
+    Function(Rc<Function>),
+    Native(Rc<NativeFunction>),
+    List(Rc<Shared<Vec<LiteralValue>>>),
+    Map(Rc<Shared<Map>>),
+    Tuple(Rc<Vec<LiteralValue>>),
+    Set(Rc<Shared<Set>>),
+    Range(range::Range),
+    Class(Rc<Class>),
+    Instance(Rc<Shared<Instance>>),
+    Iterator(Rc<RefCell<NativeIterator>>),
+    Generator(Rc<RefCell<Generator>>),
+    Enum(Rc<enums::Enum>),
+    /// The constructor of an enum variant that has fields.
+    Variant(Rc<enums::Enum>, usize),
+    EnumValue(Rc<enums::EnumValue>),
+    Trait(Rc<Trait>),
+    Module(Rc<module::Module>),
+    Done,
 }
 use LiteralValue::*;
 
@@ -26,6 +99,102 @@ fn unwrap_as_string(literal: Option<scanner::LiteralValue>) -> String {
     }
 }
 
+/// The largest magnitude up to which every integer is exactly representable
+/// as a `Number`.
+const MAX_EXACT_INTEGER: i64 = 1 << 24;
+
+fn as_integer(x: f32, operator: &Token) -> Result<i64, String> {
+    if x.fract() != 0.0 {
+        return Err(format!(
+            "Operands of '{}' must be integers, got {}",
+            operator.lexeme, x
+        ));
+    }
+    if x.abs() > MAX_EXACT_INTEGER as f32 {
+        return Err(format!(
+            "Operands of '{}' must be integers between -{} and {}, got {}",
+            operator.lexeme, MAX_EXACT_INTEGER, MAX_EXACT_INTEGER, x
+        ));
+    }
+
+    Ok(x as i64)
+}
+
+/// Converts the integer result of `operator` back to a `Number`, failing
+/// rather than rounding when it can't be represented exactly.
+fn from_integer(result: i128, operator: &Token) -> Result<LiteralValue, String> {
+    if result.abs() > MAX_EXACT_INTEGER as i128 {
+        return Err(format!(
+            "Result of '{}' must be between -{} and {}, got {}",
+            operator.lexeme, MAX_EXACT_INTEGER, MAX_EXACT_INTEGER, result
+        ));
+    }
+
+    Ok(Number(result as f32))
+}
+
+fn bitwise(x: f32, operator: &Token, y: f32) -> Result<LiteralValue, String> {
+    let x = as_integer(x, operator)? as i128;
+    let y = as_integer(y, operator)?;
+
+    let result = match operator.token_type {
+        TokenType::Ampersand => x & y as i128,
+        TokenType::Pipe => x | y as i128,
+        TokenType::Caret => x ^ y as i128,
+        TokenType::LessLess | TokenType::GreaterGreater => {
+            if !(0..64).contains(&y) {
+                return Err(format!(
+                    "Shift amount for '{}' must be between 0 and 63, got {}",
+                    operator.lexeme, y
+                ));
+            }
+            if operator.token_type == TokenType::LessLess {
+                x << y
+            } else {
+                x >> y
+            }
+        }
+        _ => return Err(format!("{} is not a bitwise operator", operator.lexeme)),
+    };
+
+    from_integer(result, operator)
+}
+
+thread_local! {
+    /// The lists, maps and sets being printed, innermost last, so that one
+    /// that contains itself prints as `[...]` rather than recursing forever.
+    static PRINTING: RefCell<Vec<*const ()>> = const { RefCell::new(vec![]) };
+    /// The pairs of lists or maps being compared, which are taken to be equal
+    /// when a cycle leads back to them.
+    static COMPARING: RefCell<Vec<(*const (), *const ())>> = const { RefCell::new(vec![]) };
+}
+
+/// Prints the contents of the container at `address` with `print`, or
+/// `cycle` when it is already being printed further out.
+fn print_once<T>(address: *const (), cycle: T, print: impl FnOnce() -> T) -> T {
+    if PRINTING.with(|printing| printing.borrow().contains(&address)) {
+        return cycle;
+    }
+
+    PRINTING.with(|printing| printing.borrow_mut().push(address));
+    let text = print();
+    PRINTING.with(|printing| printing.borrow_mut().pop());
+    text
+}
+
+/// Compares the containers at `x` and `y` with `compare`, or returns `cycle`
+/// when they are already being compared further out.
+fn compare_once<T>(x: *const (), y: *const (), cycle: T, compare: impl FnOnce() -> T) -> T {
+    if COMPARING.with(|comparing| comparing.borrow().contains(&(x, y))) {
+        return cycle;
+    }
+
+    COMPARING.with(|comparing| comparing.borrow_mut().push((x, y)));
+    let equal = compare();
+    COMPARING.with(|comparing| comparing.borrow_mut().pop());
+    equal
+}
+
 impl LiteralValue {
     pub fn to_string(&self) -> String {
         match self {
@@ -34,6 +203,146 @@ impl LiteralValue {
             LiteralValue::True => "true".to_string(),
             LiteralValue::False => "false".to_string(),
             LiteralValue::Nil => "nil".to_string(),
+            LiteralValue::Function(function) => format!("<fn {}>", function.name.lexeme),
+            LiteralValue::Native(native) => format!("<native fn {}>", native.name),
+            LiteralValue::List(items) => {
+                print_once(Rc::as_ptr(items) as *const (), "[...]".to_string(), || {
+                    format!(
+                        "[{}]",
+                        items
+                            .borrow()
+                            .iter()
+                            .map(|item| item.to_repr())
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    )
+                })
+            }
+            LiteralValue::Tuple(items) if items.len() == 1 => {
+                format!("({},)", items[0].to_repr())
+            }
+            LiteralValue::Tuple(items) => format!(
+                "({})",
+                items
+                    .iter()
+                    .map(|item| item.to_repr())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            LiteralValue::Range(range) => range.to_string(),
+            LiteralValue::Class(class) => format!("<class {}>", class.name),
+            LiteralValue::Instance(instance) => {
+                format!("<{} instance>", instance.borrow().class.name)
+            }
+            LiteralValue::Iterator(_) => "<iterator>".to_string(),
+            LiteralValue::Generator(generator) => {
+                format!("<generator {}>", generator.borrow().name.lexeme)
+            }
+            LiteralValue::Enum(enum_type) => format!("<enum {}>", enum_type.name),
+            LiteralValue::Variant(enum_type, index) => format!(
+                "<variant {}.{}>",
+                enum_type.name, enum_type.variants[*index].name
+            ),
+            LiteralValue::EnumValue(value) => value.to_string(),
+            LiteralValue::Trait(implemented) => format!("<trait {}>", implemented.name),
+            LiteralValue::Module(module) => format!("<module {}>", module.path),
+            LiteralValue::Done => "done".to_string(),
+            LiteralValue::Set(set) => {
+                print_once(Rc::as_ptr(set) as *const (), "set(...)".to_string(), || {
+                    format!(
+                        "set({})",
+                        set.borrow()
+                            .items()
+                            .iter()
+                            .map(|item| item.to_repr())
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    )
+                })
+            }
+            LiteralValue::Map(map) => {
+                print_once(Rc::as_ptr(map) as *const (), "{...}".to_string(), || {
+                    format!(
+                        "{{{}}}",
+                        map.borrow()
+                            .entries()
+                            .iter()
+                            .map(|(key, value)| format!("{}: {}", key.to_repr(), value.to_repr()))
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    )
+                })
+            }
+        }
+    }
+
+    /// Like `to_string`, but quotes strings so they stay distinguishable
+    /// when printed inside a collection.
+    pub fn to_repr(self: &Self) -> String {
+        match self {
+            StringValue(s) => format!("\"{}\"", s),
+            _ => self.to_string(),
+        }
+    }
+
+    pub fn equals(self: &Self, other: &Self) -> bool {
+        match (self, other) {
+            (Number(x), Number(y)) => x == y,
+            (StringValue(x), StringValue(y)) => x == y,
+            (True, True) | (False, False) | (Nil, Nil) | (Done, Done) => true,
+            (Class(x), Class(y)) => Rc::ptr_eq(x, y),
+            (Instance(x), Instance(y)) => Rc::ptr_eq(x, y),
+            (Iterator(x), Iterator(y)) => Rc::ptr_eq(x, y),
+            (Generator(x), Generator(y)) => Rc::ptr_eq(x, y),
+            (Function(x), Function(y)) => Rc::ptr_eq(x, y),
+            (Native(x), Native(y)) => Rc::ptr_eq(x, y),
+            (Enum(x), Enum(y)) => Rc::ptr_eq(x, y),
+            (Trait(x), Trait(y)) => Rc::ptr_eq(x, y),
+            (Module(x), Module(y)) => Rc::ptr_eq(x, y),
+            (Variant(x, i), Variant(y, j)) => Rc::ptr_eq(x, y) && i == j,
+            (EnumValue(x), EnumValue(y)) => x.equals(y),
+            (List(x), List(y)) => {
+                Rc::ptr_eq(x, y)
+                    || compare_once(
+                        Rc::as_ptr(x) as *const (),
+                        Rc::as_ptr(y) as *const (),
+                        true,
+                        || {
+                            let (x, y) = (x.borrow(), y.borrow());
+                            x.len() == y.len() && x.iter().zip(y.iter()).all(|(a, b)| a.equals(b))
+                        },
+                    )
+            }
+            (Tuple(x), Tuple(y)) => {
+                x.len() == y.len() && x.iter().zip(y.iter()).all(|(a, b)| a.equals(b))
+            }
+            (Range(x), Range(y)) => x == y,
+            (Set(x), Set(y)) => {
+                Rc::ptr_eq(x, y) || {
+                    let (x, y) = (x.borrow(), y.borrow());
+                    x.len() == y.len()
+                        && x.items()
+                            .iter()
+                            .all(|item| matches!(y.contains(item), Ok(true)))
+                }
+            }
+            (Map(x), Map(y)) => {
+                Rc::ptr_eq(x, y)
+                    || compare_once(
+                        Rc::as_ptr(x) as *const (),
+                        Rc::as_ptr(y) as *const (),
+                        true,
+                        || {
+                            let (x, y) = (x.borrow(), y.borrow());
+                            x.len() == y.len()
+                                && x.entries().iter().all(|(key, value)| match y.get(key) {
+                                    Ok(Some(other)) => value.equals(&other),
+                                    _ => false,
+                                })
+                        },
+                    )
+            }
+            _ => false,
         }
     }
 
@@ -56,17 +365,21 @@ impl LiteralValue {
         }
     }
 
+    pub fn is_truthy(self: &Self) -> bool {
+        matches!(self.is_false(), False)
+    }
+
     pub fn is_false(self: &Self) -> LiteralValue {
         match self {
             Number(x) => {
-                if *x == 0.0 as f32 {
+                if *x == 0.0_f32 {
                     True
                 } else {
                     False
                 }
             }
             StringValue(s) => {
-                if s.len() == 0 {
+                if s.is_empty() {
                     True
                 } else {
                     False
@@ -75,26 +388,886 @@ impl LiteralValue {
             True => False,
             False => True,
             Nil => True,
+            Function(_) | Native(_) => False,
+            List(items) => LiteralValue::from_bool(items.borrow().is_empty()),
+            Map(map) => LiteralValue::from_bool(map.borrow().is_empty()),
+            Tuple(items) => LiteralValue::from_bool(items.is_empty()),
+            Set(set) => LiteralValue::from_bool(set.borrow().is_empty()),
+            Range(range) => LiteralValue::from_bool(range.is_empty()),
+            Class(_) | Instance(_) | Iterator(_) | Generator(_) => False,
+            Enum(_) | Variant(..) | EnumValue(_) | Trait(_) | Module(_) => False,
+            Done => True,
+        }
+    }
+}
+
+fn compound_operator(operator: &Token) -> Token {
+    let (token_type, lexeme) = match operator.token_type {
+        TokenType::PlusEqual | TokenType::PlusPlus => (TokenType::Plus, "+"),
+        TokenType::MinusEqual | TokenType::MinusMinus => (TokenType::Minus, "-"),
+        TokenType::StarEqual => (TokenType::Star, "*"),
+        TokenType::SlashEqual => (TokenType::Slash, "/"),
+        TokenType::PercentEqual => (TokenType::Percent, "%"),
+        _ => (operator.token_type, operator.lexeme.as_str()),
+    };
+
+    Token::new(token_type, lexeme.to_string(), None, operator.line_number)
+}
+
+/// Whether `value` is an instance of `kind`, a class or a trait its class
+/// implements, or a value of `kind`, an enum.
+fn is_a(value: &LiteralValue, kind: &LiteralValue) -> Result<LiteralValue, String> {
+    let result = match (value, kind) {
+        (Instance(instance), Class(class)) => Rc::ptr_eq(&instance.borrow().class, class),
+        (Instance(instance), Trait(implemented)) => instance.borrow().class.implements(implemented),
+        (EnumValue(value), Enum(enum_type)) => Rc::ptr_eq(&value.enum_type, enum_type),
+        (_, Class(_) | Trait(_) | Enum(_)) => false,
+        _ => {
+            return Err(format!(
+                "Right operand of 'is' must be a class, trait or enum, got {}",
+                kind.to_string()
+            ))
+        }
+    };
+
+    Ok(LiteralValue::from_bool(result))
+}
+
+fn binary_operation(
+    left: &LiteralValue,
+    operator: &Token,
+    right: &LiteralValue,
+) -> Result<LiteralValue, String> {
+    match (left, operator.token_type, right) {
+        (_, TokenType::Is, _) => is_a(left, right),
+        (Number(x), TokenType::Plus, Number(y)) => Ok(Number(x + y)),
+        (Number(x), TokenType::Minus, Number(y)) => Ok(Number(x - y)),
+        (Number(x), TokenType::Star, Number(y)) => Ok(Number(x * y)),
+        (Number(x), TokenType::Slash, Number(y)) => Ok(Number(x / y)),
+        (Number(x), TokenType::Percent, Number(y)) => Ok(Number(x % y)),
+        (Number(x), TokenType::Greater, Number(y)) => Ok(LiteralValue::from_bool(x > y)),
+        (Number(x), TokenType::GreaterEqual, Number(y)) => Ok(LiteralValue::from_bool(x >= y)),
+        (Number(x), TokenType::Less, Number(y)) => Ok(LiteralValue::from_bool(x < y)),
+        (Number(x), TokenType::LessEqual, Number(y)) => Ok(LiteralValue::from_bool(x <= y)),
+        (Number(x), TokenType::BangEqual, Number(y)) => Ok(LiteralValue::from_bool(x != y)),
+        (Number(x), TokenType::EqualEqual, Number(y)) => Ok(LiteralValue::from_bool(x == y)),
+        (Set(x), TokenType::Pipe | TokenType::Ampersand | TokenType::Minus, Set(y)) => {
+            let (x, y) = (x.borrow(), y.borrow());
+            let result = match operator.token_type {
+                TokenType::Pipe => x.union(&y)?,
+                TokenType::Ampersand => x.intersection(&y)?,
+                _ => x.difference(&y)?,
+            };
+            Ok(Set(Rc::new(Shared::new(result))))
+        }
+        (
+            Number(x),
+            TokenType::Ampersand
+            | TokenType::Pipe
+            | TokenType::Caret
+            | TokenType::LessLess
+            | TokenType::GreaterGreater,
+            Number(y),
+        ) => bitwise(*x, operator, *y),
+        (
+            _,
+            TokenType::Ampersand
+            | TokenType::Pipe
+            | TokenType::Caret
+            | TokenType::LessLess
+            | TokenType::GreaterGreater,
+            _,
+        ) => Err(format!(
+            "Operands of '{}' must be integers, got {} and {}",
+            operator.lexeme,
+            left.to_string(),
+            right.to_string()
+        )),
+        (StringValue(_), operator, Number(_)) => Err(format!(
+            "{} cannot operate between String and Number",
+            operator
+        )),
+        (Number(_), operator, StringValue(_)) => Err(format!(
+            "{} cannot operate between Number and String",
+            operator
+        )),
+        (StringValue(x), TokenType::Plus, StringValue(y)) => Ok(StringValue(format!("{}{}", x, y))),
+        (StringValue(x), TokenType::BangEqual, StringValue(y)) => {
+            Ok(LiteralValue::from_bool(x != y))
+        }
+        (StringValue(x), TokenType::EqualEqual, StringValue(y)) => {
+            Ok(LiteralValue::from_bool(x == y))
+        }
+        (_, TokenType::EqualEqual, _) => Ok(LiteralValue::from_bool(left.equals(right))),
+        (_, TokenType::BangEqual, _) => Ok(LiteralValue::from_bool(!left.equals(right))),
+        _ => Err(format!(
+            "{} is not supported between {} and {}",
+            operator.lexeme,
+            left.to_string(),
+            right.to_string()
+        )),
+    }
+}
+
+/// Converts a `String` error into a `TypeError` on the line of `token`.
+fn type_error(token: &Token) -> impl Fn(String) -> RuntimeError + '_ {
+    move |msg| RuntimeError::new("TypeError", msg).at(token)
+}
+
+/// Calls the method `name` when `value` is an instance whose class defines
+/// it, which is how classes overload operators. Returns None otherwise.
+fn call_special(
+    value: &LiteralValue,
+    name: &str,
+    arguments: Vec<LiteralValue>,
+    token: &Token,
+) -> Result<Option<LiteralValue>, RuntimeError> {
+    call_method(value, name, arguments).map_err(|error| error.at(token))
+}
+
+/// Calls the method `name` of `value` if it is an instance whose class
+/// defines it, for callers without a token to blame.
+fn call_method(
+    value: &LiteralValue,
+    name: &str,
+    arguments: Vec<LiteralValue>,
+) -> Result<Option<LiteralValue>, RuntimeError> {
+    let instance = match value {
+        Instance(instance) => instance,
+        _ => return Ok(None),
+    };
+    let method = match instance.borrow().class.find_method(name) {
+        Some(method) => method,
+        None => return Ok(None),
+    };
+
+    method.bind(instance.clone()).call(arguments).map(Some)
+}
+
+/// Applies a binary operator, dispatching to `__add__`, `__eq__`, `__lt__`
+/// and the like when the left operand is an instance. Equality and
+/// comparisons also try the right operand, with `a < b` asking
+/// `b.__gt__(a)`.
+fn operate(
+    left: &LiteralValue,
+    operator: &Token,
+    right: &LiteralValue,
+) -> Result<LiteralValue, RuntimeError> {
+    let (method, reflected) = match operator.token_type {
+        TokenType::Plus => ("__add__", None),
+        TokenType::Minus => ("__sub__", None),
+        TokenType::Star => ("__mul__", None),
+        TokenType::Slash => ("__div__", None),
+        TokenType::Percent => ("__mod__", None),
+        TokenType::EqualEqual => {
+            return values_equal(left, right)
+                .map(LiteralValue::from_bool)
+                .map_err(|error| error.at(operator))
+        }
+        TokenType::BangEqual => {
+            return values_equal(left, right)
+                .map(|equal| LiteralValue::from_bool(!equal))
+                .map_err(|error| error.at(operator))
+        }
+        TokenType::Less => ("__lt__", Some("__gt__")),
+        TokenType::LessEqual => ("__le__", Some("__ge__")),
+        TokenType::Greater => ("__gt__", Some("__lt__")),
+        TokenType::GreaterEqual => ("__ge__", Some("__le__")),
+        _ => return binary_operation(left, operator, right).map_err(type_error(operator)),
+    };
+
+    let mut result = call_special(left, method, vec![right.clone()], operator)?;
+    if let (None, Some(reflected)) = (&result, reflected) {
+        result = call_special(right, reflected, vec![left.clone()], operator)?;
+    }
+
+    match result {
+        Some(result) => Ok(result),
+        None => binary_operation(left, operator, right).map_err(type_error(operator)),
+    }
+}
+
+/// Whether `left == right`, asking `__eq__` of either operand when it is an
+/// instance that defines it. Lists, tuples, maps and enum values compare
+/// their elements the same way.
+pub fn values_equal(left: &LiteralValue, right: &LiteralValue) -> Result<bool, RuntimeError> {
+    if let Some(result) = call_method(left, "__eq__", vec![right.clone()])? {
+        return Ok(result.is_truthy());
+    }
+    if let Some(result) = call_method(right, "__eq__", vec![left.clone()])? {
+        return Ok(result.is_truthy());
+    }
+
+    let all_equal = |x: &[LiteralValue], y: &[LiteralValue]| -> Result<bool, RuntimeError> {
+        if x.len() != y.len() {
+            return Ok(false);
+        }
+        for (a, b) in x.iter().zip(y) {
+            if !values_equal(a, b)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    };
+
+    // Elements are copied out first, since `__eq__` may modify the containers.
+    match (left, right) {
+        (List(x), List(y)) if !Rc::ptr_eq(x, y) => compare_once(
+            Rc::as_ptr(x) as *const (),
+            Rc::as_ptr(y) as *const (),
+            Ok(true),
+            || {
+                let (x, y) = (x.borrow().clone(), y.borrow().clone());
+                all_equal(&x, &y)
+            },
+        ),
+        (Tuple(x), Tuple(y)) => all_equal(x, y),
+        (EnumValue(x), EnumValue(y)) => {
+            Ok(x.same_variant(&y.enum_type, y.variant) && all_equal(&x.values, &y.values)?)
+        }
+        (Map(x), Map(y)) if !Rc::ptr_eq(x, y) => compare_once(
+            Rc::as_ptr(x) as *const (),
+            Rc::as_ptr(y) as *const (),
+            Ok(true),
+            || {
+                if x.borrow().len() != y.borrow().len() {
+                    return Ok(false);
+                }
+                let entries = x.borrow().entries().to_vec();
+                for (key, value) in entries {
+                    let other = y.borrow().get(&key).ok().flatten();
+                    match other {
+                        Some(other) if values_equal(&value, &other)? => (),
+                        _ => return Ok(false),
+                    }
+                }
+                Ok(true)
+            },
+        ),
+        _ => Ok(left.equals(right)),
+    }
+}
+
+/// Reads `object[index]`, calling `__index__` on instances.
+fn index_value(
+    object: &LiteralValue,
+    index: &LiteralValue,
+    bracket: &Token,
+) -> Result<LiteralValue, RuntimeError> {
+    match call_special(object, "__index__", vec![index.clone()], bracket)? {
+        Some(value) => Ok(value),
+        None => get_index(object, index, bracket),
+    }
+}
+
+/// Stores into `object[index]`, calling `__setindex__` on instances.
+fn store_index(
+    object: &LiteralValue,
+    index: &LiteralValue,
+    value: LiteralValue,
+    bracket: &Token,
+) -> Result<(), RuntimeError> {
+    match call_special(
+        object,
+        "__setindex__",
+        vec![index.clone(), value.clone()],
+        bracket,
+    )? {
+        Some(_) => Ok(()),
+        None => set_index(object, index, value, bracket),
+    }
+}
+
+/// The text `print` shows for `value`: what `__str__` returns for instances
+/// whose class defines it, also inside lists, tuples, sets and maps, or else
+/// `to_string`.
+pub fn display(value: &LiteralValue, token: &Token) -> Result<String, RuntimeError> {
+    // Elements are copied out first, since `__str__` may modify the containers.
+    let join = |items: &[LiteralValue]| -> Result<String, RuntimeError> {
+        let items = items
+            .iter()
+            .map(|item| display_item(item, token))
+            .collect::<Result<Vec<String>, RuntimeError>>()?;
+        Ok(items.join(", "))
+    };
+
+    match value {
+        Instance(_) => match call_special(value, "__str__", vec![], token)? {
+            Some(StringValue(text)) => Ok(text),
+            Some(other) => Err(RuntimeError::new(
+                "TypeError",
+                format!("__str__ must return a string, got {}", other.to_string()),
+            )
+            .at(token)),
+            None => Ok(value.to_string()),
+        },
+        List(items) => print_once(
+            Rc::as_ptr(items) as *const (),
+            Ok("[...]".to_string()),
+            || {
+                let items = items.borrow().clone();
+                Ok(format!("[{}]", join(&items)?))
+            },
+        ),
+        Tuple(items) if items.len() == 1 => Ok(format!("({},)", join(items)?)),
+        Tuple(items) => Ok(format!("({})", join(items)?)),
+        Set(set) => print_once(
+            Rc::as_ptr(set) as *const (),
+            Ok("set(...)".to_string()),
+            || {
+                let items = set.borrow().items().to_vec();
+                Ok(format!("set({})", join(&items)?))
+            },
+        ),
+        Map(map) => print_once(
+            Rc::as_ptr(map) as *const (),
+            Ok("{...}".to_string()),
+            || {
+                let entries = map.borrow().entries().to_vec();
+                let entries = entries
+                    .iter()
+                    .map(|(key, value)| {
+                        Ok(format!(
+                            "{}: {}",
+                            display_item(key, token)?,
+                            display_item(value, token)?
+                        ))
+                    })
+                    .collect::<Result<Vec<String>, RuntimeError>>()?;
+                Ok(format!("{{{}}}", entries.join(", ")))
+            },
+        ),
+        _ => Ok(value.to_string()),
+    }
+}
+
+/// Like `display`, but quotes strings, as elements of collections are.
+fn display_item(value: &LiteralValue, token: &Token) -> Result<String, RuntimeError> {
+    match value {
+        StringValue(_) => Ok(value.to_repr()),
+        _ => display(value, token),
+    }
+}
+
+pub fn call_value(
+    callee: LiteralValue,
+    arguments: Vec<LiteralValue>,
+    paren: &Token,
+) -> Result<LiteralValue, RuntimeError> {
+    call_with_named(callee, arguments, vec![], paren)
+}
+
+/// Calls `callee` with positional and `name: value` arguments.
+pub fn call_with_named(
+    callee: LiteralValue,
+    arguments: Vec<LiteralValue>,
+    named: Vec<(Token, LiteralValue)>,
+    paren: &Token,
+) -> Result<LiteralValue, RuntimeError> {
+    match callee {
+        Native(native) => {
+            if let Some((name, _)) = named.first() {
+                return Err(RuntimeError::new(
+                    "ArgumentError",
+                    format!(
+                        "Native function {} doesn't take named arguments, got '{}'",
+                        native.name, name.lexeme
+                    ),
+                )
+                .at(name));
+            }
+            if let Some(arity) = native.arity {
+                if arguments.len() != arity {
+                    return Err(RuntimeError::new(
+                        "ArgumentError",
+                        format!("Expected {} arguments but got {}", arity, arguments.len()),
+                    )
+                    .at(paren));
+                }
+            }
+
+            native.call(arguments).map_err(|error| error.at(paren))
+        }
+        Function(function) => function.call_with(arguments, named, paren),
+        Class(class) => Class::call(class, arguments, named, paren),
+        Variant(enum_type, index) => {
+            enums::Enum::construct(enum_type, index, arguments, named, paren)
+        }
+        _ => Err(RuntimeError::new(
+            "TypeError",
+            format!(
+                "Can only call functions and classes, got {}",
+                callee.to_string()
+            ),
+        )
+        .at(paren)),
+    }
+}
+
+fn evaluate_arguments(
+    arguments: &[Expr],
+    environment: &Rc<RefCell<Environment>>,
+) -> Result<Vec<LiteralValue>, RuntimeError> {
+    arguments
+        .iter()
+        .map(|arg| arg.evaluate(environment.clone()))
+        .collect()
+}
+
+fn evaluate_named_arguments(
+    named: &[(Token, Expr)],
+    environment: &Rc<RefCell<Environment>>,
+) -> Result<Vec<(Token, LiteralValue)>, RuntimeError> {
+    named
+        .iter()
+        .map(|(name, arg)| Ok((name.clone(), arg.evaluate(environment.clone())?)))
+        .collect()
+}
+
+fn get_index(
+    object: &LiteralValue,
+    index: &LiteralValue,
+    bracket: &Token,
+) -> Result<LiteralValue, RuntimeError> {
+    let index_error = |msg: String| RuntimeError::new("IndexError", msg).at(bracket);
+
+    if let Range(range) = index {
+        return get_range(object, range).map_err(index_error);
+    }
+
+    match object {
+        Range(range) => {
+            let i = builtins::resolve_index(index, range.len()).map_err(index_error)?;
+            Ok(Number(range.get(i)))
+        }
+        List(items) => {
+            let items = items.borrow();
+            let i = builtins::resolve_index(index, items.len()).map_err(index_error)?;
+            Ok(items[i].clone())
+        }
+        StringValue(s) => {
+            let chars: Vec<char> = s.chars().collect();
+            let i = builtins::resolve_index(index, chars.len()).map_err(index_error)?;
+            Ok(StringValue(chars[i].to_string()))
+        }
+        Tuple(items) => {
+            let i = builtins::resolve_index(index, items.len()).map_err(index_error)?;
+            Ok(items[i].clone())
+        }
+        Map(map) => Ok(map.borrow().get(index).map_err(index_error)?.unwrap_or(Nil)),
+        _ => Err(index_error(format!(
+            "Cannot index into {}",
+            object.to_string()
+        ))),
+    }
+}
+
+/// Selects the elements at each index produced by `range`, as in `xs[1..4]`.
+fn get_range(object: &LiteralValue, range: &range::Range) -> Result<LiteralValue, String> {
+    let indices = |len: usize| -> Result<Vec<usize>, String> {
+        (0..range.len())
+            .map(|i| builtins::resolve_index(&Number(range.get(i)), len))
+            .collect()
+    };
+
+    match object {
+        List(items) => {
+            let items = items.borrow();
+            let selected = indices(items.len())?
+                .into_iter()
+                .map(|i| items[i].clone())
+                .collect();
+            Ok(List(Rc::new(Shared::new(selected))))
+        }
+        Tuple(items) => {
+            let selected = indices(items.len())?
+                .into_iter()
+                .map(|i| items[i].clone())
+                .collect();
+            Ok(Tuple(Rc::new(selected)))
+        }
+        StringValue(s) => {
+            let chars: Vec<char> = s.chars().collect();
+            let selected = indices(chars.len())?
+                .into_iter()
+                .map(|i| chars[i])
+                .collect();
+            Ok(StringValue(selected))
+        }
+        _ => Err(format!("Cannot slice {}", object.to_string())),
+    }
+}
+
+fn set_index(
+    object: &LiteralValue,
+    index: &LiteralValue,
+    value: LiteralValue,
+    bracket: &Token,
+) -> Result<(), RuntimeError> {
+    let index_error = |msg: String| RuntimeError::new("IndexError", msg).at(bracket);
+    builtins::check_mutable(object).map_err(index_error)?;
+
+    match object {
+        List(items) => {
+            let len = items.borrow().len();
+            let i = builtins::resolve_index(index, len).map_err(index_error)?;
+            items.borrow_mut()[i] = value;
+            Ok(())
+        }
+        Map(map) => map
+            .borrow_mut()
+            .insert(index.clone(), value)
+            .map_err(index_error),
+        _ => Err(index_error(format!(
+            "Cannot assign to an index of {}",
+            object.to_string()
+        ))),
+    }
+}
+
+fn get_slice(
+    object: &LiteralValue,
+    start: Option<LiteralValue>,
+    end: Option<LiteralValue>,
+    bracket: &Token,
+) -> Result<LiteralValue, RuntimeError> {
+    let index_error = |msg: String| RuntimeError::new("IndexError", msg).at(bracket);
+
+    match object {
+        List(items) => {
+            let items = items.borrow();
+            let (start, end) =
+                builtins::resolve_slice(start, end, items.len()).map_err(index_error)?;
+            Ok(List(Rc::new(Shared::new(items[start..end].to_vec()))))
+        }
+        StringValue(s) => {
+            let chars: Vec<char> = s.chars().collect();
+            let (start, end) =
+                builtins::resolve_slice(start, end, chars.len()).map_err(index_error)?;
+            Ok(StringValue(chars[start..end].iter().collect()))
+        }
+        Tuple(items) => {
+            let (start, end) =
+                builtins::resolve_slice(start, end, items.len()).map_err(index_error)?;
+            Ok(Tuple(Rc::new(items[start..end].to_vec())))
+        }
+        _ => Err(index_error(format!("Cannot slice {}", object.to_string()))),
+    }
+}
+
+/// Splits a tuple or list into exactly `count` values for destructuring.
+pub fn destructure(
+    value: LiteralValue,
+    count: usize,
+    paren: &Token,
+) -> Result<Vec<LiteralValue>, RuntimeError> {
+    let type_error = |msg: String| RuntimeError::new("TypeError", msg).at(paren);
+    let values = match &value {
+        Tuple(items) => items.as_ref().clone(),
+        List(items) => items.borrow().clone(),
+        _ => {
+            return Err(type_error(format!(
+                "Cannot destructure {}",
+                value.to_string()
+            )))
+        }
+    };
+
+    if values.len() != count {
+        return Err(type_error(format!(
+            "Expected {} values to destructure but got {}",
+            count,
+            values.len()
+        )));
+    }
+
+    Ok(values)
+}
+
+fn get_property(object: LiteralValue, name: &Token) -> Result<LiteralValue, RuntimeError> {
+    let value = match object {
+        Instance(instance) => return Instance::get(&instance, name),
+        Class(class) => return Class::get(&class, name),
+        List(items) => builtins::list_method(items, name),
+        Map(map) => builtins::map_method(map, name),
+        Set(set) => builtins::set_method(set, name),
+        Range(range) => builtins::range_method(range, name),
+        Iterator(iterator) => builtins::iterator_method(iterator, name),
+        Generator(generator) => builtins::generator_method(generator, name),
+        Enum(enum_type) => enums::Enum::get(&enum_type, name),
+        EnumValue(value) => enums::EnumValue::get(&value, name),
+        Module(module) => module::Module::get(&module, name),
+        _ => Err(format!(
+            "Only instances have properties, got {}",
+            object.to_string()
+        )),
+    };
+
+    value
+        .map_err(error::kind("PropertyError"))
+        .map_err(|error| error.at(name))
+}
+
+fn set_property(
+    object: &LiteralValue,
+    name: &Token,
+    value: LiteralValue,
+) -> Result<(), RuntimeError> {
+    match object {
+        Instance(instance) => {
+            builtins::check_mutable(object)
+                .map_err(error::kind("PropertyError"))
+                .map_err(|error| error.at(name))?;
+            Instance::set(instance, name, value)
+        }
+        _ => Err(RuntimeError::new(
+            "PropertyError",
+            format!("Only instances have fields, got {}", object.to_string()),
+        )
+        .at(name)),
+    }
+}
+
+fn assign_target(
+    target: &Expr,
+    value: LiteralValue,
+    environment: &Rc<RefCell<Environment>>,
+) -> Result<(), RuntimeError> {
+    match target {
+        Expr::Variable { name } => assign_variable(name, value, environment),
+        Expr::Index {
+            object,
+            bracket,
+            index,
+        } => {
+            let object = object.evaluate(environment.clone())?;
+            let index = index.evaluate(environment.clone())?;
+            store_index(&object, &index, value, bracket)
+        }
+        Expr::Get {
+            object,
+            name,
+            optional: false,
+        } => {
+            let object = object.evaluate(environment.clone())?;
+            set_property(&object, name, value)
+        }
+        _ => Err(format!("Invalid assignment target {}", target.to_string()).into()),
+    }
+}
+
+/// Reads the current value of an assignable `target`, stores the first value
+/// returned by `update` back into it and evaluates to the second one. The
+/// target's sub-expressions are evaluated only once.
+fn update_target(
+    target: &Expr,
+    operator: &Token,
+    environment: &Rc<RefCell<Environment>>,
+    update: impl FnOnce(LiteralValue) -> Result<(LiteralValue, LiteralValue), RuntimeError>,
+) -> Result<LiteralValue, RuntimeError> {
+    match target {
+        Expr::Variable { name } => {
+            let current = lookup_variable(name, environment)?;
+            let (new_value, result) = update(current)?;
+            assign_variable(name, new_value, environment)?;
+            Ok(result)
+        }
+        Expr::Index {
+            object,
+            bracket,
+            index,
+        } => {
+            let object = object.evaluate(environment.clone())?;
+            let index = index.evaluate(environment.clone())?;
+            let current = index_value(&object, &index, bracket)?;
+            let (new_value, result) = update(current)?;
+            store_index(&object, &index, new_value, bracket)?;
+            Ok(result)
         }
+        Expr::Get {
+            object,
+            name,
+            optional: false,
+        } => {
+            let object = object.evaluate(environment.clone())?;
+            let current = get_property(object.clone(), name)?;
+            let (new_value, result) = update(current)?;
+            set_property(&object, name, new_value)?;
+            Ok(result)
+        }
+        _ => Err(RuntimeError::from(format!(
+            "Invalid assignment target for '{}'",
+            operator.lexeme
+        ))
+        .at(operator)),
+    }
+}
+
+pub fn lookup_variable(
+    name: &Token,
+    environment: &Rc<RefCell<Environment>>,
+) -> Result<LiteralValue, RuntimeError> {
+    match environment.borrow().get(&name.lexeme) {
+        Some(value) => Ok(value),
+        None => Err(RuntimeError::new(
+            "NameError",
+            format!("Undefined variable '{}'", name.lexeme),
+        )
+        .at(name)),
+    }
+}
+
+fn assign_variable(
+    name: &Token,
+    value: LiteralValue,
+    environment: &Rc<RefCell<Environment>>,
+) -> Result<(), RuntimeError> {
+    let result = environment.borrow_mut().assign(&name.lexeme, value);
+    match result {
+        Ok(()) => Ok(()),
+        Err(AssignError::Undefined) => Err(RuntimeError::new(
+            "NameError",
+            format!("Undefined variable '{}'", name.lexeme),
+        )
+        .at(name)),
+        Err(AssignError::Constant) => Err(RuntimeError::new(
+            "TypeError",
+            format!("Can't assign to constant '{}'", name.lexeme),
+        )
+        .at(name)),
     }
 }
 
 pub enum Expr {
+    Assign {
+        name: Token,
+        value: Box<Expr>,
+    },
     Binary {
         left: Box<Expr>,
         operator: Token,
         right: Box<Expr>,
     },
+    Call {
+        callee: Box<Expr>,
+        paren: Token,
+        arguments: Vec<Expr>,
+        named: Vec<(Token, Expr)>,
+    },
+    /// `[element for x in iterable if condition]`, or a map comprehension
+    /// when `value` is set.
+    Comprehension {
+        keyword: Token,
+        element: Box<Expr>,
+        value: Option<Box<Expr>>,
+        names: Vec<Token>,
+        paren: Option<Token>,
+        iterable: Box<Expr>,
+        condition: Option<Box<Expr>>,
+    },
+    Get {
+        object: Box<Expr>,
+        name: Token,
+        optional: bool,
+    },
     Grouping {
         expression: Box<Expr>,
     },
+    Index {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+    },
+    /// `fun (a, b) { ... }` or `(a, b) => a + b`, evaluating to a closure.
+    Lambda {
+        keyword: Token,
+        params: Rc<Vec<Param>>,
+        body: Rc<Vec<Stmt>>,
+        is_generator: bool,
+    },
+    List {
+        elements: Vec<Expr>,
+    },
+    Map {
+        brace: Token,
+        entries: Vec<(Expr, Expr)>,
+    },
     Literal {
         value: LiteralValue,
     },
+    /// `match subject { pattern if guard => body, ... }`.
+    Match {
+        keyword: Token,
+        subject: Box<Expr>,
+        arms: Rc<Vec<MatchArm>>,
+    },
+    Logical {
+        left: Box<Expr>,
+        operator: Token,
+        right: Box<Expr>,
+    },
+    Pipeline {
+        left: Box<Expr>,
+        operator: Token,
+        right: Box<Expr>,
+    },
+    Range {
+        start: Box<Expr>,
+        operator: Token,
+        end: Box<Expr>,
+        step: Option<Box<Expr>>,
+    },
+    Set {
+        object: Box<Expr>,
+        name: Token,
+        value: Box<Expr>,
+    },
+    This {
+        keyword: Token,
+    },
+    SetIndex {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+        value: Box<Expr>,
+    },
+    Slice {
+        object: Box<Expr>,
+        bracket: Token,
+        start: Option<Box<Expr>>,
+        end: Option<Box<Expr>>,
+    },
     Unary {
         operator: Token,
         right: Box<Expr>,
     },
+    Conditional {
+        condition: Box<Expr>,
+        then_branch: Box<Expr>,
+        else_branch: Box<Expr>,
+    },
+    Destructure {
+        targets: Vec<Expr>,
+        paren: Token,
+        value: Box<Expr>,
+    },
+    Tuple {
+        paren: Token,
+        elements: Vec<Expr>,
+    },
+    CompoundAssign {
+        target: Box<Expr>,
+        operator: Token,
+        value: Box<Expr>,
+    },
+    Increment {
+        target: Box<Expr>,
+        operator: Token,
+        prefix: bool,
+    },
+    Variable {
+        name: Token,
+    },
 }
 
 impl Expr {
@@ -104,6 +1277,7 @@ impl Expr {
 
     pub fn to_string(self: &Self) -> String {
         match self {
+            Expr::Assign { name, value } => format!("(= {} {})", name.lexeme, value.to_string()),
             Expr::Binary {
                 left,
                 operator,
@@ -116,86 +1290,680 @@ impl Expr {
                     right.to_string()
                 )
             }
+            Expr::Call {
+                callee,
+                paren: _,
+                arguments,
+                named,
+            } => {
+                let mut parts = vec![callee.to_string()];
+                parts.extend(arguments.iter().map(|arg| arg.to_string()));
+                parts.extend(
+                    named
+                        .iter()
+                        .map(|(name, arg)| format!("(: {} {})", name.lexeme, arg.to_string())),
+                );
+                format!("(call {})", parts.join(" "))
+            }
+            Expr::Get {
+                object,
+                name,
+                optional,
+            } => format!(
+                "({} {} {})",
+                if *optional { "?." } else { "." },
+                object.to_string(),
+                name.lexeme
+            ),
             Expr::Grouping { expression } => {
                 format!("(group {})", (*expression).to_string())
             }
-            Expr::Literal { value } => {
-                format!("{}", value.to_string())
+            Expr::Index {
+                object,
+                bracket: _,
+                index,
+            } => format!("(index {} {})", object.to_string(), index.to_string()),
+            Expr::Lambda {
+                keyword: _,
+                params,
+                body,
+                is_generator,
+            } => format!(
+                "({} ({}) {})",
+                if *is_generator { "fun*" } else { "fun" },
+                params
+                    .iter()
+                    .map(|param| param.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" "),
+                body.iter()
+                    .map(|stmt| stmt.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            ),
+            Expr::List { elements } => {
+                let mut parts = vec!["list".to_string()];
+                parts.extend(elements.iter().map(|element| element.to_string()));
+                format!("({})", parts.join(" "))
+            }
+            Expr::Comprehension {
+                keyword: _,
+                element,
+                value,
+                names,
+                paren,
+                iterable,
+                condition,
+            } => {
+                let names = names
+                    .iter()
+                    .map(|name| name.lexeme.clone())
+                    .collect::<Vec<String>>()
+                    .join(" ");
+                let mut parts = match value {
+                    Some(value) => vec![
+                        "map".to_string(),
+                        format!("({} {})", element.to_string(), value.to_string()),
+                    ],
+                    None => vec!["list".to_string(), element.to_string()],
+                };
+                parts.push(format!(
+                    "(for {} {})",
+                    if paren.is_some() {
+                        format!("({})", names)
+                    } else {
+                        names
+                    },
+                    iterable.to_string()
+                ));
+                if let Some(condition) = condition {
+                    parts.push(format!("(if {})", condition.to_string()));
+                }
+                format!("({})", parts.join(" "))
+            }
+            Expr::Map { brace: _, entries } => {
+                let mut parts = vec!["map".to_string()];
+                parts.extend(
+                    entries
+                        .iter()
+                        .map(|(key, value)| format!("({} {})", key.to_string(), value.to_string())),
+                );
+                format!("({})", parts.join(" "))
+            }
+            Expr::Range {
+                start,
+                operator,
+                end,
+                step,
+            } => match step {
+                Some(step) => format!(
+                    "({} {} {} {})",
+                    operator.lexeme,
+                    start.to_string(),
+                    end.to_string(),
+                    step.to_string()
+                ),
+                None => format!(
+                    "({} {} {})",
+                    operator.lexeme,
+                    start.to_string(),
+                    end.to_string()
+                ),
+            },
+            Expr::Set {
+                object,
+                name,
+                value,
+            } => format!(
+                "(= (. {} {}) {})",
+                object.to_string(),
+                name.lexeme,
+                value.to_string()
+            ),
+            Expr::This { keyword: _ } => "this".to_string(),
+            Expr::SetIndex {
+                object,
+                bracket: _,
+                index,
+                value,
+            } => format!(
+                "(= (index {} {}) {})",
+                object.to_string(),
+                index.to_string(),
+                value.to_string()
+            ),
+            Expr::Slice {
+                object,
+                bracket: _,
+                start,
+                end,
+            } => {
+                let bound = |bound: &Option<Box<Expr>>| match bound {
+                    Some(expr) => expr.to_string(),
+                    None => "_".to_string(),
+                };
+                format!(
+                    "(slice {} {} {})",
+                    object.to_string(),
+                    bound(start),
+                    bound(end)
+                )
+            }
+            Expr::Literal { value } => value.to_string(),
+            Expr::Match {
+                keyword: _,
+                subject,
+                arms,
+            } => {
+                let mut parts = vec!["match".to_string(), subject.to_string()];
+                parts.extend(arms.iter().map(|arm| match &arm.guard {
+                    Some(guard) => format!(
+                        "({} (if {}) => {})",
+                        arm.pattern.to_string(),
+                        guard.to_string(),
+                        arm.body.to_string()
+                    ),
+                    None => format!("({} => {})", arm.pattern.to_string(), arm.body.to_string()),
+                }));
+                format!("({})", parts.join(" "))
             }
+            Expr::Logical {
+                left,
+                operator,
+                right,
+            } => format!(
+                "({} {} {})",
+                operator.lexeme,
+                left.to_string(),
+                right.to_string()
+            ),
+            Expr::Pipeline {
+                left,
+                operator,
+                right,
+            } => format!(
+                "({} {} {})",
+                operator.lexeme,
+                left.to_string(),
+                right.to_string()
+            ),
             Expr::Unary { operator, right } => {
                 let operator_str = operator.lexeme.clone();
                 let right_str = (*right).to_string();
                 format!("({} {})", operator_str, right_str)
             }
+            Expr::Destructure {
+                targets,
+                paren: _,
+                value,
+            } => format!(
+                "(= (tuple {}) {})",
+                targets
+                    .iter()
+                    .map(|target| target.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" "),
+                value.to_string()
+            ),
+            Expr::Tuple { paren: _, elements } => {
+                let mut parts = vec!["tuple".to_string()];
+                parts.extend(elements.iter().map(|element| element.to_string()));
+                format!("({})", parts.join(" "))
+            }
+            Expr::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => format!(
+                "(? {} {} {})",
+                condition.to_string(),
+                then_branch.to_string(),
+                else_branch.to_string()
+            ),
+            Expr::CompoundAssign {
+                target,
+                operator,
+                value,
+            } => format!(
+                "({} {} {})",
+                operator.lexeme,
+                target.to_string(),
+                value.to_string()
+            ),
+            Expr::Increment {
+                target,
+                operator,
+                prefix,
+            } => {
+                if *prefix {
+                    format!("({} {})", operator.lexeme, target.to_string())
+                } else {
+                    format!("({} {})", target.to_string(), operator.lexeme)
+                }
+            }
+            Expr::Variable { name } => name.lexeme.clone(),
         }
     }
 
-    pub fn evaluate(self: &Self) -> Result<LiteralValue, String> {
+    /// The value of a literal, or of a negated number literal, known without
+    /// evaluating anything.
+    pub fn constant(self: &Self) -> Option<LiteralValue> {
         match self {
-            Expr::Literal { value } => Ok((*value).clone()),
-            Expr::Grouping { expression } => expression.evaluate(),
-            Expr::Unary { operator, right } => {
-                let right = right.evaluate()?;
+            Expr::Literal { value } => Some(value.clone()),
+            Expr::Grouping { expression } => expression.constant(),
+            Expr::Unary { operator, right } if operator.token_type == TokenType::Minus => {
+                match right.constant() {
+                    Some(Number(x)) => Some(Number(-x)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
 
-                match (&right, operator.token_type) {
-                    (Number(x), TokenType::Minus) => Ok(Number(-x)),
-                    (_, TokenType::Minus) => {
-                        return Err(format!("Minus not implemented for  {}", right.to_string()))
+    pub fn is_assignment_target(self: &Self) -> bool {
+        matches!(
+            self,
+            Expr::Variable { .. }
+                | Expr::Index { .. }
+                | Expr::Get {
+                    optional: false,
+                    ..
+                }
+        )
+    }
+
+    /// Evaluates a property access or call chain. Returns `None` when an
+    /// optional link (`?.`) met nil, so the rest of the chain is skipped.
+    fn evaluate_chain(
+        self: &Self,
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<Option<LiteralValue>, RuntimeError> {
+        match self {
+            Expr::Get {
+                object,
+                name,
+                optional,
+            } => {
+                let object = match object.evaluate_chain(environment)? {
+                    Some(object) => object,
+                    None => return Ok(None),
+                };
+
+                match object {
+                    Nil if *optional => Ok(None),
+                    _ => Ok(Some(get_property(object, name)?)),
+                }
+            }
+            Expr::Call {
+                callee,
+                paren,
+                arguments,
+                named,
+            } => {
+                let callee = match callee.evaluate_chain(environment.clone())? {
+                    Some(callee) => callee,
+                    None => return Ok(None),
+                };
+                let arguments = evaluate_arguments(arguments, &environment)?;
+                let named = evaluate_named_arguments(named, &environment)?;
+
+                Ok(Some(call_with_named(callee, arguments, named, paren)?))
+            }
+            Expr::Index {
+                object,
+                bracket,
+                index,
+            } => {
+                let object = match object.evaluate_chain(environment.clone())? {
+                    Some(object) => object,
+                    None => return Ok(None),
+                };
+                let index = index.evaluate(environment)?;
+
+                Ok(Some(index_value(&object, &index, bracket)?))
+            }
+            Expr::Slice {
+                object,
+                bracket,
+                start,
+                end,
+            } => {
+                let object = match object.evaluate_chain(environment.clone())? {
+                    Some(object) => object,
+                    None => return Ok(None),
+                };
+                let start = match start {
+                    Some(start) => Some(start.evaluate(environment.clone())?),
+                    None => None,
+                };
+                let end = match end {
+                    Some(end) => Some(end.evaluate(environment)?),
+                    None => None,
+                };
+
+                Ok(Some(get_slice(&object, start, end, bracket)?))
+            }
+            _ => Ok(Some(self.evaluate(environment)?)),
+        }
+    }
+
+    pub fn evaluate(
+        self: &Self,
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<LiteralValue, RuntimeError> {
+        match self {
+            Expr::Get { .. } | Expr::Call { .. } | Expr::Index { .. } | Expr::Slice { .. } => {
+                Ok(self.evaluate_chain(environment)?.unwrap_or(Nil))
+            }
+            Expr::Lambda {
+                keyword,
+                params,
+                body,
+                is_generator,
+            } => {
+                let mut name = keyword.clone();
+                name.lexeme = "lambda".to_string();
+
+                Ok(Function(Rc::new(function::Function {
+                    name,
+                    params: params.clone(),
+                    body: body.clone(),
+                    closure: environment,
+                    is_initializer: false,
+                    is_generator: *is_generator,
+                })))
+            }
+            Expr::List { elements } => {
+                let items = evaluate_arguments(elements, &environment)?;
+                Ok(List(Rc::new(Shared::new(items))))
+            }
+            Expr::Comprehension {
+                keyword,
+                element,
+                value,
+                names,
+                paren,
+                iterable,
+                condition,
+            } => {
+                let iterable = iterable.evaluate(environment.clone())?;
+                let iterator = builtins::iter(&iterable).map_err(|error| error.at(keyword))?;
+
+                let mut items = vec![];
+                let mut map = Map::new();
+                while let Some(item) = builtins::next(&iterator)? {
+                    // The loop variables live in their own scope, fresh for each item.
+                    let mut scope = Environment::with_enclosing(environment.clone());
+                    match paren {
+                        Some(paren) => {
+                            let values = destructure(item, names.len(), paren)?;
+                            for (name, value) in names.iter().zip(values) {
+                                scope.define(name.lexeme.clone(), value);
+                            }
+                        }
+                        None => scope.define(names[0].lexeme.clone(), item),
+                    }
+                    let scope = Rc::new(RefCell::new(scope));
+
+                    if let Some(condition) = condition {
+                        if !condition.evaluate(scope.clone())?.is_truthy() {
+                            continue;
+                        }
+                    }
+
+                    let element = element.evaluate(scope.clone())?;
+                    match value {
+                        Some(value) => map
+                            .insert(element, value.evaluate(scope)?)
+                            .map_err(type_error(keyword))?,
+                        None => items.push(element),
                     }
-                    (any, TokenType::Bang) => Ok(any.is_false()),
-                    (_, ttype) => Err(format!("{} is not a valid unary operator", ttype)),
+                }
+
+                match value {
+                    Some(_) => Ok(Map(Rc::new(Shared::new(map)))),
+                    None => Ok(List(Rc::new(Shared::new(items)))),
                 }
             }
-            Expr::Binary {
+            Expr::Map { brace, entries } => {
+                let mut map = Map::new();
+                for (key, value) in entries {
+                    let key = key.evaluate(environment.clone())?;
+                    let value = value.evaluate(environment.clone())?;
+                    map.insert(key, value).map_err(type_error(brace))?;
+                }
+                Ok(Map(Rc::new(Shared::new(map))))
+            }
+            Expr::Range {
+                start,
+                operator,
+                end,
+                step,
+            } => {
+                let start = start.evaluate(environment.clone())?;
+                let end = end.evaluate(environment.clone())?;
+                let step = match step {
+                    Some(step) => step.evaluate(environment)?,
+                    None => Number(1.0),
+                };
+
+                match (&start, &end, &step) {
+                    (Number(start), Number(end), Number(step)) => Ok(Range(
+                        range::Range::new(
+                            *start,
+                            *end,
+                            *step,
+                            operator.token_type == TokenType::DotDotEqual,
+                        )
+                        .map_err(type_error(operator))?,
+                    )),
+                    _ => Err(type_error(operator)(format!(
+                        "Range bounds and step must be numbers, got {}, {} and {}",
+                        start.to_string(),
+                        end.to_string(),
+                        step.to_string()
+                    ))),
+                }
+            }
+            Expr::Set {
+                object,
+                name,
+                value,
+            } => {
+                let object = object.evaluate(environment.clone())?;
+                let value = value.evaluate(environment)?;
+                set_property(&object, name, value.clone())?;
+                Ok(value)
+            }
+            Expr::This { keyword } => lookup_variable(keyword, &environment),
+            Expr::SetIndex {
+                object,
+                bracket,
+                index,
+                value,
+            } => {
+                let object = object.evaluate(environment.clone())?;
+                let index = index.evaluate(environment.clone())?;
+                let value = value.evaluate(environment)?;
+                store_index(&object, &index, value.clone(), bracket)?;
+                Ok(value)
+            }
+            Expr::Pipeline {
                 left,
                 operator,
                 right,
             } => {
-                let left = left.evaluate()?;
-                let right = right.evaluate()?;
+                let value = left.evaluate(environment.clone())?;
 
-                match (&left, operator.token_type, &right) {
-                    (Number(x), TokenType::Plus, Number(y)) => Ok(Number(x + y)),
-                    (Number(x), TokenType::Minus, Number(y)) => Ok(Number(x - y)),
-                    (Number(x), TokenType::Star, Number(y)) => Ok(Number(x * y)),
-                    (Number(x), TokenType::Slash, Number(y)) => Ok(Number(x / y)),
-                    (Number(x), TokenType::Greater, Number(y)) => {
-                        Ok(LiteralValue::from_bool(x > y))
+                match right.as_ref() {
+                    Expr::Call {
+                        callee,
+                        paren,
+                        arguments,
+                        named,
+                    } => {
+                        let callee = callee.evaluate(environment.clone())?;
+                        let mut all_arguments = vec![value];
+                        all_arguments.extend(evaluate_arguments(arguments, &environment)?);
+                        let named = evaluate_named_arguments(named, &environment)?;
+
+                        call_with_named(callee, all_arguments, named, paren)
                     }
-                    (Number(x), TokenType::GreaterEqual, Number(y)) => {
-                        Ok(LiteralValue::from_bool(x >= y))
+                    _ => {
+                        let callee = right.evaluate(environment)?;
+                        call_value(callee, vec![value], operator)
                     }
-                    (Number(x), TokenType::Less, Number(y)) => Ok(LiteralValue::from_bool(x < y)),
-                    (Number(x), TokenType::LessEqual, Number(y)) => {
-                        Ok(LiteralValue::from_bool(x <= y))
+                }
+            }
+            Expr::Match {
+                keyword,
+                subject,
+                arms,
+            } => {
+                let subject = subject.evaluate(environment.clone())?;
+
+                for arm in arms.iter() {
+                    let mut bindings = vec![];
+                    let matched = arm
+                        .pattern
+                        .matches(&subject, &mut bindings, &environment)
+                        .map_err(|error| error.at(keyword))?;
+                    if !matched {
+                        continue;
                     }
-                    (Number(x), TokenType::BangEqual, Number(y)) => {
-                        Ok(LiteralValue::from_bool(x != y))
+
+                    // Each arm binds its names in a scope of its own.
+                    let mut scope = Environment::with_enclosing(environment.clone());
+                    for (name, value) in bindings {
+                        scope.define(name, value);
                     }
-                    (Number(x), TokenType::EqualEqual, Number(y)) => {
-                        Ok(LiteralValue::from_bool(x == y))
+                    let scope = Rc::new(RefCell::new(scope));
+
+                    if let Some(guard) = &arm.guard {
+                        if !guard.evaluate(scope.clone())?.is_truthy() {
+                            continue;
+                        }
                     }
-                    (StringValue(_), operator, Number(_)) => Err(format!(
-                        "{} cannot operate between String and Number",
-                        operator
-                    )),
-                    (Number(_), operator, StringValue(_)) => Err(format!(
-                        "{} cannot operate between Number and String",
-                        operator
-                    )),
-                    (StringValue(x), TokenType::Plus, StringValue(y)) => {
-                        Ok(StringValue(format!("{}{}", x, y)))
+                    return arm.body.evaluate(scope);
+                }
+
+                Err(RuntimeError::new(
+                    "MatchError",
+                    format!("No pattern matched {}", subject.to_repr()),
+                )
+                .at(keyword))
+            }
+            Expr::Logical {
+                left,
+                operator,
+                right,
+            } => {
+                let left = left.evaluate(environment.clone())?;
+
+                match (left, operator.token_type) {
+                    (Nil, TokenType::QuestionQuestion) => right.evaluate(environment),
+                    (left, TokenType::QuestionQuestion) => Ok(left),
+                    (_, ttype) => Err(format!("{} is not a valid logical operator", ttype).into()),
+                }
+            }
+            Expr::Assign { name, value } => {
+                let value = value.evaluate(environment.clone())?;
+                assign_variable(name, value.clone(), &environment)?;
+                Ok(value)
+            }
+            Expr::Variable { name } => lookup_variable(name, &environment),
+            Expr::Literal { value } => Ok((*value).clone()),
+            Expr::Grouping { expression } => expression.evaluate(environment),
+            Expr::Unary { operator, right } => {
+                let right = right.evaluate(environment)?;
+                if operator.token_type == TokenType::Minus {
+                    if let Some(result) = call_special(&right, "__neg__", vec![], operator)? {
+                        return Ok(result);
                     }
-                    (StringValue(x), TokenType::BangEqual, StringValue(y)) => {
-                        Ok(LiteralValue::from_bool(x != y))
+                }
+
+                let result = match (&right, operator.token_type) {
+                    (Number(x), TokenType::Minus) => Ok(Number(-x)),
+                    (_, TokenType::Minus) => {
+                        Err(format!("Minus not implemented for  {}", right.to_string()))
                     }
-                    (StringValue(x), TokenType::EqualEqual, StringValue(y)) => {
-                        Ok(LiteralValue::from_bool(x == y))
+                    (any, TokenType::Bang) => Ok(any.is_false()),
+                    (Number(x), TokenType::Tilde) => {
+                        as_integer(*x, operator).and_then(|x| from_integer(!x as i128, operator))
                     }
-                    _ => todo!(),
+                    (_, TokenType::Tilde) => Err(format!(
+                        "Operand of '~' must be an integer, got {}",
+                        right.to_string()
+                    )),
+                    (_, ttype) => Err(format!("{} is not a valid unary operator", ttype)),
+                };
+                result.map_err(type_error(operator))
+            }
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                let left = left.evaluate(environment.clone())?;
+                let right = right.evaluate(environment)?;
+
+                operate(&left, operator, &right)
+            }
+            Expr::Destructure {
+                targets,
+                paren,
+                value,
+            } => {
+                let value = value.evaluate(environment.clone())?;
+                let values = destructure(value.clone(), targets.len(), paren)?;
+                for (target, value) in targets.iter().zip(values) {
+                    assign_target(target, value, &environment)?;
+                }
+                Ok(value)
+            }
+            Expr::Tuple { paren: _, elements } => {
+                let items = evaluate_arguments(elements, &environment)?;
+                Ok(Tuple(Rc::new(items)))
+            }
+            Expr::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                if condition.evaluate(environment.clone())?.is_truthy() {
+                    then_branch.evaluate(environment)
+                } else {
+                    else_branch.evaluate(environment)
                 }
             }
-            _ => Err("Can't perform binary operation on given values".to_string()),
+            Expr::CompoundAssign {
+                target,
+                operator,
+                value,
+            } => update_target(target, operator, &environment, |current| {
+                let value = value.evaluate(environment.clone())?;
+                let result = operate(&current, &compound_operator(operator), &value)?;
+                Ok((result.clone(), result))
+            }),
+            Expr::Increment {
+                target,
+                operator,
+                prefix,
+            } => update_target(target, operator, &environment, |current| {
+                let result = match current {
+                    Number(_) => {
+                        binary_operation(&current, &compound_operator(operator), &Number(1.0))
+                    }
+                    _ => Err(format!(
+                        "Operand of '{}' must be a number, got {}",
+                        operator.lexeme,
+                        current.to_string()
+                    )),
+                }
+                .map_err(type_error(operator))?;
+                Ok((result.clone(), if *prefix { result } else { current }))
+            }),
         }
     }
 }
@@ -241,4 +2009,47 @@ mod tests {
 
         assert_eq!(result, "(* (- 123) (group 45.67))");
     }
+
+    fn evaluate_source(source: &str) -> Result<LiteralValue, String> {
+        let tokens = crate::Scanner::new(&format!("{};", source)).scan_tokens()?;
+        let environment = Rc::new(RefCell::new(Environment::new()));
+        match crate::Parser::new(tokens).parse_program()?.as_slice() {
+            [Stmt::Expression { expression }] => Ok(expression.evaluate(environment)?),
+            _ => panic!("Expected a single expression in {}", source),
+        }
+    }
+
+    #[test]
+    fn handle_bitwise_operators() {
+        let cases = [
+            ("6 & 3", 2.0),
+            ("6 | 3", 7.0),
+            ("6 ^ 3", 5.0),
+            ("~5", -6.0),
+            ("1 << 4", 16.0),
+            ("-16 >> 2", -4.0),
+            ("1 | 2 + 4", 7.0),
+            ("1 << 24", 16777216.0),
+            ("~-16777216", 16777215.0),
+        ];
+
+        for (source, expected) in cases {
+            match evaluate_source(source) {
+                Ok(LiteralValue::Number(x)) => assert_eq!(x, expected, "{}", source),
+                other => panic!("{} evaluated to {:?}", source, other),
+            }
+        }
+    }
+
+    #[test]
+    fn handle_bitwise_non_integer_operands() {
+        assert!(evaluate_source("1.5 & 1").is_err());
+        assert!(evaluate_source("~0.5").is_err());
+        assert!(evaluate_source("\"a\" | 1").is_err());
+        assert!(evaluate_source("1 << 64").is_err());
+        assert!(evaluate_source("1 << 40").is_err());
+        assert!(evaluate_source("16777218 | 0").is_err());
+        assert!(evaluate_source("~16777216").is_err());
+        assert!(evaluate_source("1 >> -1").is_err());
+    }
 }
@@ -0,0 +1,266 @@
+use crate::class::Instance;
+use crate::environment::Environment;
+use crate::error::RuntimeError;
+use crate::expr::{LiteralValue, Shared};
+use crate::generator::Generator;
+use crate::interpreter::{Flow, Interpreter};
+use crate::scanner::Token;
+use crate::stmt::{Param, Stmt};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+/// How many calls may be nested before a `RecursionError`, so that runaway
+/// recursion fails in the script instead of overflowing the native stack.
+pub const MAX_CALL_DEPTH: usize = 1000;
+
+/// The native stack size programs run with, enough for `MAX_CALL_DEPTH`
+/// nested calls.
+pub const STACK_SIZE: usize = 1 << 30;
+
+thread_local! {
+    static CALL_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Runs `call` to function `name`, made at `line`, one level deeper in the
+/// call stack of the program, failing once `MAX_CALL_DEPTH` calls are nested.
+pub fn nested_call<T>(
+    name: &str,
+    line: usize,
+    call: impl FnOnce() -> Result<T, RuntimeError>,
+) -> Result<T, RuntimeError> {
+    let depth = CALL_DEPTH.with(|depth| depth.get());
+    if depth >= MAX_CALL_DEPTH {
+        return Err(RuntimeError::Error {
+            kind: "RecursionError".to_string(),
+            message: format!(
+                "Maximum call depth of {} exceeded calling {}",
+                MAX_CALL_DEPTH, name
+            ),
+            line: Some(line),
+        });
+    }
+
+    CALL_DEPTH.with(|current| current.set(depth + 1));
+    let result = call();
+    CALL_DEPTH.with(|current| current.set(depth));
+    result
+}
+
+pub struct Function {
+    pub name: Token,
+    pub params: Rc<Vec<Param>>,
+    pub body: Rc<Vec<Stmt>>,
+    pub closure: Rc<RefCell<Environment>>,
+    pub is_initializer: bool,
+    pub is_generator: bool,
+}
+
+impl Function {
+    /// Describes how many positional arguments this function accepts, for
+    /// arity errors.
+    fn expected_arguments(self: &Self, required: usize, positional: usize) -> String {
+        if self.params.iter().any(|param| param.variadic) {
+            format!("at least {}", required)
+        } else if required == positional {
+            format!("{}", required)
+        } else {
+            format!("{} to {}", required, positional)
+        }
+    }
+
+    /// Binds positional and `name: value` arguments to the parameters in a
+    /// new scope. Missing parameters take their defaults, evaluated in that
+    /// scope so they can refer to earlier parameters; extra positional
+    /// arguments are collected into the variadic parameter.
+    fn bind_arguments(
+        self: &Self,
+        arguments: Vec<LiteralValue>,
+        named: Vec<(Token, LiteralValue)>,
+        paren: &Token,
+    ) -> Result<Rc<RefCell<Environment>>, RuntimeError> {
+        let argument_error =
+            |message, token: &Token| RuntimeError::new("ArgumentError", message).at(token);
+        let positional = self.params.iter().filter(|param| !param.variadic).count();
+        let variadic = self.params.iter().any(|param| param.variadic);
+        let required = self
+            .params
+            .iter()
+            .filter(|param| param.default.is_none() && !param.variadic)
+            .count();
+        let count = arguments.len();
+        if (count > positional && !variadic) || (count < required && named.is_empty()) {
+            return Err(argument_error(
+                format!(
+                    "Expected {} arguments but got {}",
+                    self.expected_arguments(required, positional),
+                    count
+                ),
+                paren,
+            ));
+        }
+
+        let mut values: Vec<Option<LiteralValue>> = vec![None; self.params.len()];
+        let mut rest = vec![];
+        for (i, argument) in arguments.into_iter().enumerate() {
+            if i < positional {
+                values[i] = Some(argument);
+            } else {
+                rest.push(argument);
+            }
+        }
+
+        for (name, value) in named {
+            let index = self
+                .params
+                .iter()
+                .position(|param| param.name.lexeme == name.lexeme && !param.variadic);
+            match index {
+                Some(index) if values[index].is_none() => values[index] = Some(value),
+                Some(_) => {
+                    return Err(argument_error(
+                        format!("Got multiple values for argument '{}'", name.lexeme),
+                        &name,
+                    ))
+                }
+                None => {
+                    return Err(argument_error(
+                        format!(
+                            "Unknown argument '{}' for {}",
+                            name.lexeme, self.name.lexeme
+                        ),
+                        &name,
+                    ))
+                }
+            }
+        }
+
+        let environment = Rc::new(RefCell::new(Environment::with_enclosing(
+            self.closure.clone(),
+        )));
+        for (param, value) in self.params.iter().zip(values) {
+            let value = match (value, &param.default) {
+                _ if param.variadic => {
+                    LiteralValue::List(Rc::new(Shared::new(std::mem::take(&mut rest))))
+                }
+                (Some(value), _) => value,
+                (None, Some(default)) => default.evaluate(environment.clone())?,
+                (None, None) => {
+                    return Err(argument_error(
+                        format!(
+                            "Missing argument '{}' for {}",
+                            param.name.lexeme, self.name.lexeme
+                        ),
+                        paren,
+                    ))
+                }
+            };
+            environment
+                .borrow_mut()
+                .define(param.name.lexeme.clone(), value);
+        }
+
+        Ok(environment)
+    }
+
+    /// Returns a copy of this method whose closure defines `this` as `instance`.
+    pub fn bind(self: &Self, instance: Rc<Shared<Instance>>) -> Function {
+        self.bind_this(LiteralValue::Instance(instance))
+    }
+
+    /// Like `bind`, for any value of `this`, such as the class of a static method.
+    pub fn bind_this(self: &Self, this: LiteralValue) -> Function {
+        let mut environment = Environment::with_enclosing(self.closure.clone());
+        environment.define("this".to_string(), this);
+
+        Function {
+            name: self.name.clone(),
+            params: self.params.clone(),
+            body: self.body.clone(),
+            closure: Rc::new(RefCell::new(environment)),
+            is_initializer: self.is_initializer,
+            is_generator: self.is_generator,
+        }
+    }
+
+    /// Calls with positional arguments only, as the runtime does for
+    /// protocol methods like `iter()` and `next()`.
+    pub fn call(self: &Self, arguments: Vec<LiteralValue>) -> Result<LiteralValue, RuntimeError> {
+        self.call_with(arguments, vec![], &self.name)
+    }
+
+    pub fn call_with(
+        self: &Self,
+        arguments: Vec<LiteralValue>,
+        named: Vec<(Token, LiteralValue)>,
+        paren: &Token,
+    ) -> Result<LiteralValue, RuntimeError> {
+        let environment = self.bind_arguments(arguments, named, paren)?;
+
+        if self.is_generator {
+            let generator = Generator::new(
+                self.name.clone(),
+                self.body.clone(),
+                self.closure.clone(),
+                environment,
+            );
+            return Ok(LiteralValue::Generator(Rc::new(RefCell::new(generator))));
+        }
+
+        let flow = nested_call(&self.name.lexeme, paren.line_number, || {
+            Interpreter::with_environment(self.closure.clone())
+                .execute_block(&self.body, environment)
+        })?;
+
+        if self.is_initializer {
+            return Ok(self
+                .closure
+                .borrow()
+                .get("this")
+                .unwrap_or(LiteralValue::Nil));
+        }
+
+        match flow {
+            Flow::Return(value) => Ok(value),
+            _ => Ok(LiteralValue::Nil),
+        }
+    }
+}
+
+pub struct NativeFunction {
+    pub name: String,
+    /// `None` for functions taking any number of arguments.
+    pub arity: Option<usize>,
+    pub fun: Box<dyn Fn(Vec<LiteralValue>) -> Result<LiteralValue, RuntimeError>>,
+}
+
+impl NativeFunction {
+    pub fn new(
+        name: &str,
+        arity: Option<usize>,
+        fun: impl Fn(Vec<LiteralValue>) -> Result<LiteralValue, RuntimeError> + 'static,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            arity,
+            fun: Box::new(fun),
+        }
+    }
+
+    pub fn call(self: &Self, arguments: Vec<LiteralValue>) -> Result<LiteralValue, RuntimeError> {
+        (self.fun)(arguments)
+    }
+}
+
+impl std::fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+impl std::fmt::Debug for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "<fn {}>", self.name.lexeme)
+    }
+}
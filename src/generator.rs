@@ -0,0 +1,100 @@
+use crate::environment::Environment;
+use crate::error::RuntimeError;
+use crate::expr::LiteralValue;
+use crate::function;
+use crate::interpreter::{Flow, Frame, Interpreter};
+use crate::scanner::Token;
+use crate::stmt::Stmt;
+use std::{cell::RefCell, rc::Rc};
+
+#[derive(PartialEq)]
+enum State {
+    Suspended,
+    Running,
+    Done,
+}
+
+/// The paused body of a generator function call. Each `next()` runs the body
+/// until the following `yield`, then saves the frames it unwound through.
+pub struct Generator {
+    pub name: Token,
+    body: Rc<Vec<Stmt>>,
+    closure: Rc<RefCell<Environment>>,
+    environment: Rc<RefCell<Environment>>,
+    frames: Vec<Frame>,
+    state: State,
+}
+
+impl Generator {
+    pub fn new(
+        name: Token,
+        body: Rc<Vec<Stmt>>,
+        closure: Rc<RefCell<Environment>>,
+        environment: Rc<RefCell<Environment>>,
+    ) -> Self {
+        Self {
+            name,
+            body,
+            closure,
+            environment,
+            frames: vec![],
+            state: State::Suspended,
+        }
+    }
+
+    /// Runs the generator to its next `yield`, returning `None` once the body
+    /// has finished. An error also finishes the generator.
+    pub fn resume(
+        generator: &Rc<RefCell<Generator>>,
+    ) -> Result<Option<LiteralValue>, RuntimeError> {
+        // The body may refer to this generator, so it must not stay borrowed while running.
+        let (body, closure, environment, frames) = {
+            let mut generator = generator.borrow_mut();
+            match generator.state {
+                State::Done => return Ok(None),
+                State::Running => {
+                    return Err(RuntimeError::new(
+                        "TypeError",
+                        format!("Generator {} is already running", generator.name.lexeme),
+                    ))
+                }
+                State::Suspended => generator.state = State::Running,
+            }
+            (
+                generator.body.clone(),
+                generator.closure.clone(),
+                generator.environment.clone(),
+                std::mem::take(&mut generator.frames),
+            )
+        };
+
+        let mut interpreter = Interpreter::with_frames(closure, frames);
+        let name = generator.borrow().name.clone();
+        let result = function::nested_call(&name.lexeme, name.line_number, || {
+            interpreter.execute_block(&body, environment)
+        });
+
+        let mut generator = generator.borrow_mut();
+        match result {
+            Ok(Flow::Yield(value)) => {
+                generator.frames = interpreter.take_frames();
+                generator.state = State::Suspended;
+                Ok(Some(value))
+            }
+            Ok(_) => {
+                generator.state = State::Done;
+                Ok(None)
+            }
+            Err(msg) => {
+                generator.state = State::Done;
+                Err(msg)
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for Generator {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "<generator {}>", self.name.lexeme)
+    }
+}
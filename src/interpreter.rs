@@ -0,0 +1,1846 @@
+use crate::builtins;
+use crate::class::Class;
+use crate::enums::{Enum, Variant};
+use crate::environment::Environment;
+use crate::error::{self, RuntimeError};
+use crate::expr::{self, Expr, LiteralValue};
+use crate::function::Function;
+use crate::module::Modules;
+use crate::scanner::Token;
+use crate::stmt::{Stmt, SwitchCase};
+use crate::traits::Trait;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+/// How a statement finished executing, so that `return` can unwind through
+/// enclosing blocks up to the function call.
+pub enum Flow {
+    Normal,
+    Return(LiteralValue),
+    /// `break` or `continue`, with the label of the loop they target if any.
+    Break(Option<String>),
+    Continue(Option<String>),
+    /// A generator suspended; the statements it unwound through are recorded
+    /// as frames so that execution can resume where it left off.
+    Yield(LiteralValue),
+}
+
+/// The saved state of one compound statement that a `yield` unwound through.
+pub enum Frame {
+    Block {
+        index: usize,
+        environment: Rc<RefCell<Environment>>,
+    },
+    If {
+        then_branch: bool,
+    },
+    While,
+    ForIn {
+        iterator: LiteralValue,
+        environment: Rc<RefCell<Environment>>,
+    },
+    Try {
+        stage: TryStage,
+        /// How the body or catch clause finished, for after `finally` runs.
+        pending: Option<Result<Flow, RuntimeError>>,
+    },
+    Yield,
+}
+
+/// The clause of a `try` statement that a generator suspended in.
+pub enum TryStage {
+    Body,
+    Catch,
+    Finally,
+}
+
+pub struct Interpreter {
+    environment: Rc<RefCell<Environment>>,
+    /// Frames of a suspended generator, innermost first. While resuming, each
+    /// compound statement pops its own frame from the end.
+    frames: Vec<Frame>,
+    /// The modules loaded by the program, and the directory that imports in
+    /// the file being run resolve against.
+    modules: Rc<RefCell<Modules>>,
+    directory: PathBuf,
+    /// The names declared with `export` so far.
+    exports: Vec<String>,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        let mut globals = Environment::new();
+        builtins::define_globals(&mut globals);
+
+        Self::with_environment(Rc::new(RefCell::new(globals)))
+    }
+
+    /// An interpreter for the script at `path`, whose imports resolve
+    /// relative to its directory.
+    pub fn for_file(path: &Path) -> Self {
+        let mut interpreter = Self::new();
+        interpreter.modules.borrow_mut().enter(path);
+        if let Some(directory) = path.parent() {
+            interpreter.directory = directory.to_path_buf();
+        }
+
+        interpreter
+    }
+
+    /// An interpreter for a module in `directory`, imported by a program
+    /// that has loaded `modules` so far.
+    pub fn for_module(modules: Rc<RefCell<Modules>>, directory: PathBuf) -> Self {
+        let mut interpreter = Self::new();
+        interpreter.modules = modules;
+        interpreter.directory = directory;
+
+        interpreter
+    }
+
+    pub fn with_environment(environment: Rc<RefCell<Environment>>) -> Self {
+        Self::with_frames(environment, vec![])
+    }
+
+    /// An interpreter that resumes a suspended generator from its frames.
+    pub fn with_frames(environment: Rc<RefCell<Environment>>, frames: Vec<Frame>) -> Self {
+        Self {
+            environment,
+            frames,
+            modules: Rc::new(RefCell::new(Modules::new())),
+            directory: PathBuf::new(),
+            exports: vec![],
+        }
+    }
+
+    pub fn environment(self: &Self) -> Rc<RefCell<Environment>> {
+        self.environment.clone()
+    }
+
+    /// Takes the names exported by the statements executed so far.
+    pub fn take_exports(self: &mut Self) -> Vec<String> {
+        std::mem::take(&mut self.exports)
+    }
+
+    /// Takes the frames recorded by the last `Flow::Yield`.
+    pub fn take_frames(self: &mut Self) -> Vec<Frame> {
+        std::mem::take(&mut self.frames)
+    }
+
+    /// Whether a `break` or `continue` with `target` applies to the loop
+    /// labelled `label`, rather than to one enclosing it.
+    fn targets_loop(target: &Option<String>, label: &Option<Token>) -> bool {
+        match (target, label) {
+            (None, _) => true,
+            (Some(target), Some(label)) => *target == label.lexeme,
+            (Some(_), None) => false,
+        }
+    }
+
+    /// The index in the switch body to start executing from: the first case
+    /// with a value equal to the subject, else `default`, if there is one.
+    fn switch_start(
+        self: &mut Self,
+        subject: &Expr,
+        cases: &[SwitchCase],
+    ) -> Result<Option<usize>, RuntimeError> {
+        let subject = subject.evaluate(self.environment.clone())?;
+        for case in cases {
+            for value in &case.values {
+                if Self::case_matches(&value.evaluate(self.environment.clone())?, &subject)? {
+                    return Ok(Some(case.start));
+                }
+            }
+        }
+
+        Ok(cases
+            .iter()
+            .find(|case| case.values.is_empty())
+            .map(|case| case.start))
+    }
+
+    /// The methods declared in a class or trait body, closing over the
+    /// current environment.
+    fn methods(self: &Self, methods: &[Stmt]) -> HashMap<String, Rc<Function>> {
+        let mut functions = HashMap::new();
+        for method in methods {
+            if let Stmt::Function {
+                name,
+                params,
+                body,
+                is_generator,
+            } = method
+            {
+                let function = Function {
+                    name: name.clone(),
+                    params: params.clone(),
+                    body: body.clone(),
+                    closure: self.environment.clone(),
+                    is_initializer: name.lexeme == "init",
+                    is_generator: *is_generator,
+                };
+                functions.insert(name.lexeme.clone(), Rc::new(function));
+            }
+        }
+
+        functions
+    }
+
+    /// Whether a case value selects `subject`: an equal value, or for enums,
+    /// the constructor of the variant the subject is.
+    fn case_matches(value: &LiteralValue, subject: &LiteralValue) -> Result<bool, RuntimeError> {
+        match (value, subject) {
+            (LiteralValue::Variant(enum_type, variant), LiteralValue::EnumValue(subject)) => {
+                Ok(subject.same_variant(enum_type, *variant))
+            }
+            _ => expr::values_equal(value, subject),
+        }
+    }
+
+    fn corrupted_frame() -> RuntimeError {
+        "Corrupted generator state".to_string().into()
+    }
+
+    pub fn interpret(self: &mut Self, stmts: Vec<Stmt>) -> Result<(), String> {
+        self.execute_module(&stmts)?;
+
+        Ok(())
+    }
+
+    /// Executes the top-level statements of a file.
+    pub fn execute_module(self: &mut Self, stmts: &[Stmt]) -> Result<(), RuntimeError> {
+        for stmt in stmts.iter() {
+            self.execute(stmt)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn execute_block(
+        self: &mut Self,
+        statements: &[Stmt],
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<Flow, RuntimeError> {
+        self.execute_block_from(statements, 0, environment)
+    }
+
+    /// Executes `statements` from index `start`, or from where a suspended
+    /// generator left off when resuming.
+    fn execute_block_from(
+        self: &mut Self,
+        statements: &[Stmt],
+        start: usize,
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<Flow, RuntimeError> {
+        let (start, environment) = match self.frames.pop() {
+            Some(Frame::Block { index, environment }) => (index, environment),
+            Some(_) => return Err(Self::corrupted_frame()),
+            None => (start, environment),
+        };
+        let old_environment = std::mem::replace(&mut self.environment, environment);
+
+        let mut result = Ok(Flow::Normal);
+        for (index, stmt) in statements.iter().enumerate().skip(start) {
+            result = self.execute(stmt);
+            match result {
+                Ok(Flow::Normal) => (),
+                Ok(Flow::Yield(_)) => {
+                    self.frames.push(Frame::Block {
+                        index,
+                        environment: self.environment.clone(),
+                    });
+                    break;
+                }
+                _ => break,
+            }
+        }
+        self.environment = old_environment;
+
+        result
+    }
+
+    fn execute(self: &mut Self, stmt: &Stmt) -> Result<Flow, RuntimeError> {
+        match stmt {
+            Stmt::Expression { expression } => {
+                expression.evaluate(self.environment.clone())?;
+            }
+            Stmt::Print {
+                keyword,
+                expression,
+            } => {
+                let value = expression.evaluate(self.environment.clone())?;
+                println!("{}", expr::display(&value, keyword)?);
+            }
+            Stmt::Var { name, initializer } => {
+                let value = initializer.evaluate(self.environment.clone())?;
+                self.environment
+                    .borrow_mut()
+                    .define(name.lexeme.clone(), value);
+            }
+            Stmt::Const { name, initializer } => {
+                let value = initializer.evaluate(self.environment.clone())?;
+                self.environment
+                    .borrow_mut()
+                    .define_constant(name.lexeme.clone(), value);
+            }
+            Stmt::Destructure {
+                names,
+                paren,
+                initializer,
+            } => {
+                let value = initializer.evaluate(self.environment.clone())?;
+                let values = expr::destructure(value, names.len(), paren)?;
+                for (name, value) in names.iter().zip(values) {
+                    self.environment
+                        .borrow_mut()
+                        .define(name.lexeme.clone(), value);
+                }
+            }
+            Stmt::Block { statements } => {
+                let new_environment = Environment::with_enclosing(self.environment.clone());
+                return self.execute_block(statements, Rc::new(RefCell::new(new_environment)));
+            }
+            Stmt::Function {
+                name,
+                params,
+                body,
+                is_generator,
+            } => {
+                let function = Function {
+                    name: name.clone(),
+                    params: params.clone(),
+                    body: body.clone(),
+                    closure: self.environment.clone(),
+                    is_initializer: false,
+                    is_generator: *is_generator,
+                };
+                self.environment.borrow_mut().define(
+                    name.lexeme.clone(),
+                    LiteralValue::Function(Rc::new(function)),
+                );
+            }
+            Stmt::Class {
+                name,
+                traits,
+                methods,
+                statics,
+                getters,
+                setters,
+            } => {
+                let mut class_methods = self.methods(methods);
+                let mut implemented = vec![];
+                for trait_name in traits {
+                    match expr::lookup_variable(trait_name, &self.environment)? {
+                        LiteralValue::Trait(value) => implemented.push(value),
+                        other => {
+                            return Err(RuntimeError::new(
+                                "TypeError",
+                                format!(
+                                    "Class {} can only implement traits, got {}",
+                                    name.lexeme,
+                                    other.to_string()
+                                ),
+                            )
+                            .at(trait_name))
+                        }
+                    }
+                }
+                Trait::implement(&implemented, &mut class_methods, name)
+                    .map_err(error::kind("TypeError"))
+                    .map_err(|error| error.at(name))?;
+
+                let class = Class {
+                    name: name.lexeme.clone(),
+                    methods: class_methods,
+                    statics: self.methods(statics),
+                    getters: self.methods(getters),
+                    setters: self.methods(setters),
+                    traits: implemented,
+                };
+                self.environment
+                    .borrow_mut()
+                    .define(name.lexeme.clone(), LiteralValue::Class(Rc::new(class)));
+            }
+            Stmt::Import {
+                keyword,
+                path,
+                alias,
+                names,
+            } => {
+                let module = Modules::load(&self.modules, &self.directory, path, keyword)?;
+                if let Some(alias) = alias {
+                    self.environment
+                        .borrow_mut()
+                        .define(alias.lexeme.clone(), LiteralValue::Module(module.clone()));
+                }
+                for name in names {
+                    let value = module.export(&name.lexeme).ok_or_else(|| {
+                        RuntimeError::new(
+                            "ImportError",
+                            format!("Module '{}' has no export '{}'", path, name.lexeme),
+                        )
+                        .at(name)
+                    })?;
+                    let mut environment = self.environment.borrow_mut();
+                    if module.environment.borrow().is_constant(&name.lexeme) {
+                        environment.define_constant(name.lexeme.clone(), value);
+                    } else {
+                        environment.define(name.lexeme.clone(), value);
+                    }
+                }
+            }
+            Stmt::Export {
+                keyword: _,
+                declaration,
+            } => {
+                self.execute(declaration)?;
+                self.exports.extend(
+                    declaration
+                        .declared_names()
+                        .iter()
+                        .map(|name| name.lexeme.clone()),
+                );
+            }
+            Stmt::Trait {
+                name,
+                required,
+                methods,
+            } => {
+                let value = Trait {
+                    name: name.lexeme.clone(),
+                    required: required.iter().map(|name| name.lexeme.clone()).collect(),
+                    methods: self.methods(methods),
+                };
+                self.environment
+                    .borrow_mut()
+                    .define(name.lexeme.clone(), LiteralValue::Trait(Rc::new(value)));
+            }
+            Stmt::Enum { name, variants } => {
+                let enum_type = Enum {
+                    name: name.lexeme.clone(),
+                    variants: variants
+                        .iter()
+                        .map(|(variant, fields)| Variant {
+                            name: variant.lexeme.clone(),
+                            fields: fields.iter().map(|field| field.lexeme.clone()).collect(),
+                        })
+                        .collect(),
+                };
+                self.environment
+                    .borrow_mut()
+                    .define(name.lexeme.clone(), LiteralValue::Enum(Rc::new(enum_type)));
+            }
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                let take_then = match self.frames.pop() {
+                    Some(Frame::If { then_branch }) => then_branch,
+                    Some(_) => return Err(Self::corrupted_frame()),
+                    None => condition.evaluate(self.environment.clone())?.is_truthy(),
+                };
+                let branch = if take_then {
+                    then_branch
+                } else if let Some(else_branch) = else_branch {
+                    else_branch
+                } else {
+                    return Ok(Flow::Normal);
+                };
+
+                let flow = self.execute(branch)?;
+                if let Flow::Yield(_) = flow {
+                    self.frames.push(Frame::If {
+                        then_branch: take_then,
+                    });
+                }
+                return Ok(flow);
+            }
+            Stmt::While {
+                label,
+                condition,
+                body,
+                increment,
+            } => {
+                let mut resuming = match self.frames.pop() {
+                    Some(Frame::While) => true,
+                    Some(_) => return Err(Self::corrupted_frame()),
+                    None => false,
+                };
+
+                while resuming || condition.evaluate(self.environment.clone())?.is_truthy() {
+                    resuming = false;
+                    match self.execute(body)? {
+                        Flow::Normal => (),
+                        flow @ Flow::Yield(_) => {
+                            self.frames.push(Frame::While);
+                            return Ok(flow);
+                        }
+                        Flow::Break(target) if Self::targets_loop(&target, label) => break,
+                        Flow::Continue(target) if Self::targets_loop(&target, label) => (),
+                        flow => return Ok(flow),
+                    }
+
+                    if let Some(increment) = increment {
+                        increment.evaluate(self.environment.clone())?;
+                    }
+                }
+            }
+            Stmt::ForIn {
+                label,
+                keyword,
+                names,
+                paren,
+                iterable,
+                body,
+            } => {
+                let (iterator, mut resumed_environment) = match self.frames.pop() {
+                    Some(Frame::ForIn {
+                        iterator,
+                        environment,
+                    }) => (iterator, Some(environment)),
+                    Some(_) => return Err(Self::corrupted_frame()),
+                    None => {
+                        let iterable = iterable.evaluate(self.environment.clone())?;
+                        let iterator =
+                            builtins::iter(&iterable).map_err(|error| error.at(keyword))?;
+                        (iterator, None)
+                    }
+                };
+
+                loop {
+                    let environment = match resumed_environment.take() {
+                        Some(environment) => environment,
+                        None => {
+                            let item = match builtins::next(&iterator)? {
+                                Some(item) => item,
+                                None => break,
+                            };
+
+                            // Each iteration gets a fresh scope so closures capture that item.
+                            let mut environment =
+                                Environment::with_enclosing(self.environment.clone());
+                            match paren {
+                                Some(paren) => {
+                                    let values = expr::destructure(item, names.len(), paren)?;
+                                    for (name, value) in names.iter().zip(values) {
+                                        environment.define(name.lexeme.clone(), value);
+                                    }
+                                }
+                                None => environment.define(names[0].lexeme.clone(), item),
+                            }
+                            Rc::new(RefCell::new(environment))
+                        }
+                    };
+
+                    match self
+                        .execute_block(std::slice::from_ref(body.as_ref()), environment.clone())?
+                    {
+                        Flow::Normal => (),
+                        flow @ Flow::Yield(_) => {
+                            self.frames.push(Frame::ForIn {
+                                iterator,
+                                environment,
+                            });
+                            return Ok(flow);
+                        }
+                        Flow::Break(target) if Self::targets_loop(&target, label) => break,
+                        Flow::Continue(target) if Self::targets_loop(&target, label) => (),
+                        flow => return Ok(flow),
+                    }
+                }
+            }
+            Stmt::Return { keyword: _, value } => {
+                let value = value.evaluate(self.environment.clone())?;
+                return Ok(Flow::Return(value));
+            }
+            Stmt::Throw { keyword, value } => {
+                let value = value.evaluate(self.environment.clone())?;
+                return Err(RuntimeError::Thrown {
+                    value,
+                    line: keyword.line_number,
+                    module: None,
+                });
+            }
+            Stmt::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                let (mut stage, mut pending) = match self.frames.pop() {
+                    Some(Frame::Try { stage, pending }) => (stage, pending),
+                    Some(_) => return Err(Self::corrupted_frame()),
+                    None => (TryStage::Body, None),
+                };
+                let mut caught = None;
+
+                if let TryStage::Body = stage {
+                    let environment = Environment::with_enclosing(self.environment.clone());
+                    match self.execute_block(body, Rc::new(RefCell::new(environment))) {
+                        flow @ Ok(Flow::Yield(_)) => {
+                            self.frames.push(Frame::Try {
+                                stage: TryStage::Body,
+                                pending: None,
+                            });
+                            return flow;
+                        }
+                        Err(error) if catch.is_some() => {
+                            caught = Some(error.into_value());
+                            stage = TryStage::Catch;
+                        }
+                        result => {
+                            pending = Some(result);
+                            stage = TryStage::Finally;
+                        }
+                    }
+                }
+
+                if let (TryStage::Catch, Some((name, handler))) = (&stage, catch) {
+                    let mut environment = Environment::with_enclosing(self.environment.clone());
+                    if let Some(value) = caught {
+                        environment.define(name.lexeme.clone(), value);
+                    }
+                    match self.execute_block(handler, Rc::new(RefCell::new(environment))) {
+                        flow @ Ok(Flow::Yield(_)) => {
+                            self.frames.push(Frame::Try {
+                                stage: TryStage::Catch,
+                                pending: None,
+                            });
+                            return flow;
+                        }
+                        result => pending = Some(result),
+                    }
+                }
+
+                if let Some(finally) = finally {
+                    let environment = Environment::with_enclosing(self.environment.clone());
+                    match self.execute_block(finally, Rc::new(RefCell::new(environment))) {
+                        flow @ Ok(Flow::Yield(_)) => {
+                            self.frames.push(Frame::Try {
+                                stage: TryStage::Finally,
+                                pending,
+                            });
+                            return flow;
+                        }
+                        Ok(Flow::Normal) => (),
+                        // A return, break or error inside `finally` replaces the pending outcome.
+                        result => return result,
+                    }
+                }
+
+                return pending.unwrap_or(Ok(Flow::Normal));
+            }
+            Stmt::Switch {
+                keyword: _,
+                subject,
+                cases,
+                body,
+            } => {
+                // When resuming, the body's block frame records where it stopped.
+                let start = if self.frames.is_empty() {
+                    match self.switch_start(subject, cases)? {
+                        Some(start) => start,
+                        None => return Ok(Flow::Normal),
+                    }
+                } else {
+                    0
+                };
+
+                let environment = Environment::with_enclosing(self.environment.clone());
+                return match self.execute_block_from(
+                    body,
+                    start,
+                    Rc::new(RefCell::new(environment)),
+                )? {
+                    Flow::Break(None) => Ok(Flow::Normal),
+                    flow => Ok(flow),
+                };
+            }
+            Stmt::Break { keyword: _, label } => {
+                return Ok(Flow::Break(
+                    label.as_ref().map(|label| label.lexeme.clone()),
+                ));
+            }
+            Stmt::Continue { keyword: _, label } => {
+                return Ok(Flow::Continue(
+                    label.as_ref().map(|label| label.lexeme.clone()),
+                ));
+            }
+            Stmt::Yield { keyword: _, value } => {
+                // Resuming right after this yield: carry on with the next statement.
+                match self.frames.pop() {
+                    Some(Frame::Yield) => return Ok(Flow::Normal),
+                    Some(_) => return Err(Self::corrupted_frame()),
+                    None => (),
+                }
+
+                let value = value.evaluate(self.environment.clone())?;
+                self.frames.push(Frame::Yield);
+                return Ok(Flow::Yield(value));
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, scanner::Scanner};
+
+    fn run(source: &str) -> Result<Interpreter, String> {
+        let tokens = Scanner::new(source).scan_tokens()?;
+        let stmts = Parser::new(tokens).parse_program()?;
+        let mut interpreter = Interpreter::new();
+        interpreter.interpret(stmts)?;
+        Ok(interpreter)
+    }
+
+    fn get(interpreter: &Interpreter, name: &str) -> String {
+        match interpreter.environment.borrow().get(name) {
+            Some(value) => value.to_string(),
+            None => panic!("Undefined variable '{}'", name),
+        }
+    }
+
+    #[test]
+    fn handle_block_scoping() {
+        let interpreter = run("var a = 1; var b = 2; { var a = 10; b = a + b; }").unwrap();
+
+        assert_eq!(get(&interpreter, "a"), "1");
+        assert_eq!(get(&interpreter, "b"), "12");
+    }
+
+    #[test]
+    fn handle_compound_assignment() {
+        let interpreter = run("var a = 10; a += 5; a -= 3; a *= 2; a /= 4; a %= 4;
+             var s = \"ab\"; s += \"cd\"; var m = 7 % 3;")
+        .unwrap();
+
+        assert_eq!(get(&interpreter, "a"), "2");
+        assert_eq!(get(&interpreter, "s"), "abcd");
+        assert_eq!(get(&interpreter, "m"), "1");
+    }
+
+    #[test]
+    fn handle_increment_and_decrement() {
+        let interpreter =
+            run("var i = 5; var pre = ++i; var post = i++; var dec = --i; var postdec = i--;")
+                .unwrap();
+
+        assert_eq!(get(&interpreter, "pre"), "6");
+        assert_eq!(get(&interpreter, "post"), "6");
+        assert_eq!(get(&interpreter, "dec"), "6");
+        assert_eq!(get(&interpreter, "postdec"), "6");
+        assert_eq!(get(&interpreter, "i"), "5");
+    }
+
+    #[test]
+    fn handle_conditional_evaluates_one_branch() {
+        let interpreter = run("var a = 0; var b = 0;
+             var x = 1 > 2 ? a++ : b++;
+             var y = \"\" ? \"yes\" : 0 ? \"zero\" : \"no\";")
+        .unwrap();
+
+        assert_eq!(get(&interpreter, "a"), "0");
+        assert_eq!(get(&interpreter, "b"), "1");
+        assert_eq!(get(&interpreter, "x"), "0");
+        assert_eq!(get(&interpreter, "y"), "no");
+    }
+
+    #[test]
+    fn handle_nil_coalescing_and_optional_chaining() {
+        let interpreter = run("var calls = 0; var missing = nil;
+             var a = missing ?? 5;
+             var b = 0 ?? calls++;
+             var c = missing?.name;
+             var d = missing?.field.method(calls++);
+             var e = (missing ?? nil)?.name ?? \"default\";")
+        .unwrap();
+
+        assert_eq!(get(&interpreter, "a"), "5");
+        assert_eq!(get(&interpreter, "b"), "0");
+        assert_eq!(get(&interpreter, "c"), "nil");
+        assert_eq!(get(&interpreter, "d"), "nil");
+        assert_eq!(get(&interpreter, "e"), "default");
+        assert_eq!(get(&interpreter, "calls"), "0");
+
+        assert!(run("var missing = nil; missing.name;").is_err());
+        assert!(run("var x = nil; (x?.a).b;").is_err());
+    }
+
+    #[test]
+    fn handle_functions_and_closures() {
+        let interpreter = run("fun make_counter() {
+                 var count = 0;
+                 fun next() { count += 1; return count; }
+                 return next;
+             }
+             var counter = make_counter();
+             counter(); counter();
+             var third = counter();
+             fun nothing() {}
+             var empty = nothing();")
+        .unwrap();
+
+        assert_eq!(get(&interpreter, "third"), "3");
+        assert_eq!(get(&interpreter, "empty"), "nil");
+        assert_eq!(get(&interpreter, "counter"), "<fn next>");
+
+        assert!(run("fun f(a, b) { return a; } f(1);").is_err());
+        assert!(run("var x = 1; x();").is_err());
+        assert!(run("return 1;").is_err());
+    }
+
+    #[test]
+    fn handle_pipeline() {
+        let interpreter = run("fun double(x) { return x * 2; }
+             fun add(x, y) { return x + y; }
+             fun sub(x, y) { return x - y; }
+             var a = 3 |> double |> add(1) |> sub(10);
+             var b = 1 + 2 |> double;")
+        .unwrap();
+
+        assert_eq!(get(&interpreter, "a"), "-3");
+        assert_eq!(get(&interpreter, "b"), "6");
+
+        assert!(run("var x = 1 |> 2;").is_err());
+    }
+
+    #[test]
+    fn handle_lists() {
+        let interpreter = run("var xs = [1, 2, 3];
+             var alias = xs;
+             alias.push(4);
+             xs[0] = 10;
+             xs[-1] += 1;
+             var calls = 0;
+             fun index() { calls++; return 1; }
+             xs[index()]++;
+             var last = xs[-1];
+             var slice = xs[1:-1];
+             var popped = xs.pop();
+             xs.insert(0, \"first\");
+             var inserted = [1, 2];
+             inserted.insert(-1, 0);
+             inserted.insert(3, 4);
+             var has = xs.contains(10);
+             xs.reverse();
+             var len = xs.len();
+             var sub = \"kinglang\"[4:];")
+        .unwrap();
+
+        assert_eq!(get(&interpreter, "alias"), "[3, 3, 10, \"first\"]");
+        assert_eq!(get(&interpreter, "last"), "5");
+        assert_eq!(get(&interpreter, "slice"), "[3, 3]");
+        assert_eq!(get(&interpreter, "popped"), "5");
+        assert_eq!(get(&interpreter, "inserted"), "[1, 0, 2, 4]");
+        assert_eq!(get(&interpreter, "has"), "true");
+        assert_eq!(get(&interpreter, "len"), "4");
+        assert_eq!(get(&interpreter, "calls"), "1");
+        assert_eq!(get(&interpreter, "sub"), "lang");
+
+        assert!(run("var xs = [1]; xs[1];").is_err());
+        assert!(run("var xs = [1]; xs[0.5];").is_err());
+        assert!(run("var xs = []; xs.pop();").is_err());
+        assert_eq!(
+            run("[1].insert(5, 1);").err().unwrap(),
+            "Index 5 out of range for length 1 at line 1"
+        );
+        assert!(run("var xs = []; xs.missing();").is_err());
+    }
+
+    #[test]
+    fn handle_maps() {
+        let interpreter = run("var m = {\"b\": 1, \"a\": 2, 3: \"three\", true: nil};
+             var alias = m;
+             alias[\"c\"] = 4;
+             m[\"b\"] += 10;
+             m[nil] = 0;
+             var keys = m.keys();
+             var values = m.values();
+             var missing = m[\"zzz\"] ?? \"default\";
+             var has = m.has(3);
+             var removed = m.remove(\"a\");
+             var len = m.len();
+             var same = {1: 2, 3: 4} == {3: 4, 1: 2};")
+        .unwrap();
+
+        assert_eq!(
+            get(&interpreter, "keys"),
+            "[\"b\", \"a\", 3, true, \"c\", nil]"
+        );
+        assert_eq!(get(&interpreter, "values"), "[11, 2, \"three\", nil, 4, 0]");
+        assert_eq!(get(&interpreter, "missing"), "default");
+        assert_eq!(get(&interpreter, "has"), "true");
+        assert_eq!(get(&interpreter, "removed"), "2");
+        assert_eq!(get(&interpreter, "len"), "5");
+        assert_eq!(
+            get(&interpreter, "alias"),
+            "{\"b\": 11, 3: \"three\", true: nil, \"c\": 4, nil: 0}"
+        );
+        assert_eq!(get(&interpreter, "same"), "true");
+
+        assert!(run("var m = {[1]: 2};").is_err());
+        assert!(run("var m = {}; m.missing();").is_err());
+    }
+
+    #[test]
+    fn handle_tuples_and_destructuring() {
+        let interpreter = run(
+            "fun divmod(a, b) { var r = a % b; return ((a - r) / b, r); }
+             var (q, r) = divmod(17, 5);
+             var (x, y) = (1, 2);
+             (x, y) = (y, x);
+             var xs = [0, 0];
+             (xs[0], xs[1]) = [\"a\", \"b\"];
+             var t = (1, \"two\", (3,));
+             var second = t[1];
+             var rest = t[1:];
+             var same = (1, 2) == (1, 2);
+             var m = {(1, 2): \"pair\"};
+             var found = m[(1, 2)];
+             var empty = ();",
+        )
+        .unwrap();
+
+        assert_eq!(get(&interpreter, "q"), "3");
+        assert_eq!(get(&interpreter, "r"), "2");
+        assert_eq!(get(&interpreter, "x"), "2");
+        assert_eq!(get(&interpreter, "y"), "1");
+        assert_eq!(get(&interpreter, "xs"), "[\"a\", \"b\"]");
+        assert_eq!(get(&interpreter, "t"), "(1, \"two\", (3,))");
+        assert_eq!(get(&interpreter, "second"), "two");
+        assert_eq!(get(&interpreter, "rest"), "(\"two\", (3,))");
+        assert_eq!(get(&interpreter, "same"), "true");
+        assert_eq!(get(&interpreter, "found"), "pair");
+        assert_eq!(get(&interpreter, "empty"), "()");
+
+        let error = run("var (a, b) = (1, 2, 3);").err().unwrap();
+        assert!(error.contains("line 1"), "{}", error);
+        assert!(run("var a = 1; var b = 2; (a, b) = (1,);").is_err());
+        assert!(run("var (a, b) = 1;").is_err());
+        assert!(run("var t = (1, 2); t[0] = 3;").is_err());
+    }
+
+    #[test]
+    fn handle_sets() {
+        let interpreter = run("var a = set(3, 1, 2, 1);
+             var b = set([2, 3, 4]);
+             var union = a | b;
+             var intersection = a & b;
+             var difference = a - b;
+             a.add(5);
+             a.add(1);
+             var removed = a.remove(3);
+             var has = a.contains(5);
+             var len = a.len();
+             var empty = set();
+             var same = set(1, 2) == set(2, 1);")
+        .unwrap();
+
+        assert_eq!(get(&interpreter, "union"), "set(3, 1, 2, 4)");
+        assert_eq!(get(&interpreter, "intersection"), "set(3, 2)");
+        assert_eq!(get(&interpreter, "difference"), "set(1)");
+        assert_eq!(get(&interpreter, "a"), "set(1, 2, 5)");
+        assert_eq!(get(&interpreter, "removed"), "true");
+        assert_eq!(get(&interpreter, "has"), "true");
+        assert_eq!(get(&interpreter, "len"), "3");
+        assert_eq!(get(&interpreter, "empty"), "set()");
+        assert_eq!(get(&interpreter, "same"), "true");
+
+        assert!(run("var s = set([1], [2]);").is_err());
+        assert!(run("var s = set(1) | 1;").is_err());
+    }
+
+    #[test]
+    fn handle_ranges() {
+        let interpreter = run("var r = 0..10 step 3;
+             var len = r.len();
+             var third = r[2];
+             var last = r[-1];
+             var has = r.contains(6);
+             var inclusive = (1..=5).len();
+             var down = (5..0 step -2).len();
+             var empty = (5..1).len();
+             var xs = [10, 20, 30, 40, 50];
+             var middle = xs[1..4];
+             var evens = xs[0..xs.len() step 2];
+             var word = \"kinglang\"[0..=3];")
+        .unwrap();
+
+        assert_eq!(get(&interpreter, "r"), "0..10 step 3");
+        assert_eq!(get(&interpreter, "len"), "4");
+        assert_eq!(get(&interpreter, "third"), "6");
+        assert_eq!(get(&interpreter, "last"), "9");
+        assert_eq!(get(&interpreter, "has"), "true");
+        assert_eq!(get(&interpreter, "inclusive"), "5");
+        assert_eq!(get(&interpreter, "down"), "3");
+        assert_eq!(get(&interpreter, "empty"), "0");
+        assert_eq!(get(&interpreter, "middle"), "[20, 30, 40]");
+        assert_eq!(get(&interpreter, "evens"), "[10, 30, 50]");
+        assert_eq!(get(&interpreter, "word"), "king");
+
+        assert!(run("var r = 0..10 step 0;").is_err());
+        assert!(run("var r = 0..\"a\";").is_err());
+        assert!(run("var xs = [1, 2]; xs[0..3];").is_err());
+    }
+
+    #[test]
+    fn handle_classes() {
+        let interpreter = run("class Counter {
+                 init(start) { this.count = start; }
+                 increment() { this.count += 1; return this; }
+             }
+             var counter = Counter(10);
+             counter.increment().increment();
+             counter.count++;
+             var count = counter.count;
+             var method = counter.increment;
+             method();
+             var after = counter.count;
+             var kind = 1 > 2 ? \"big\" : \"small\";
+             if (count > 12) { kind = \"big\"; } else { kind = \"small\"; }
+             var total = 0;
+             while (total < 5) total += 2;")
+        .unwrap();
+
+        assert_eq!(get(&interpreter, "count"), "13");
+        assert_eq!(get(&interpreter, "after"), "14");
+        assert_eq!(get(&interpreter, "counter"), "<Counter instance>");
+        assert_eq!(get(&interpreter, "Counter"), "<class Counter>");
+        assert_eq!(get(&interpreter, "kind"), "big");
+        assert_eq!(get(&interpreter, "total"), "6");
+
+        assert!(run("class A {} A().missing;").is_err());
+        assert!(run("class A { init(x) {} } A();").is_err());
+        assert!(run("print this;").is_err());
+    }
+
+    #[test]
+    fn handle_for_in_over_built_in_types() {
+        let interpreter = run("var out = [];
+             for (x in [1, 2]) out.push(x);
+             for (c in \"ab\") out.push(c);
+             for (k in {\"k1\": 1, \"k2\": 2}) out.push(k);
+             for (x in (3, 4)) out.push(x);
+             for (x in set(5, 5, 6)) out.push(x);
+             for (x in 7..=8) out.push(x);
+             for ((a, b) in [(9, 10)]) { out.push(a + b); }
+             var it = [11, 12].iter();
+             var first = it.next();
+             for (x in it) out.push(x);
+             var finished = it.next() == done;
+             var grow = [1];
+             for (x in grow) if (x < 3) grow.push(x + 1);")
+        .unwrap();
+
+        assert_eq!(
+            get(&interpreter, "out"),
+            "[1, 2, \"a\", \"b\", \"k1\", \"k2\", 3, 4, 5, 6, 7, 8, 19, 12]"
+        );
+        assert_eq!(get(&interpreter, "first"), "11");
+        assert_eq!(get(&interpreter, "finished"), "true");
+        assert_eq!(get(&interpreter, "grow"), "[1, 2, 3]");
+
+        assert!(run("for (x in 1) print x;").is_err());
+        assert!(run("for ((a, b) in [1]) print a;").is_err());
+    }
+
+    #[test]
+    fn handle_for_in_over_user_defined_iterators() {
+        let interpreter = run("class Countdown {
+                 init(from) { this.current = from; }
+                 next() {
+                     if (this.current == 0) return done;
+                     this.current -= 1;
+                     return this.current + 1;
+                 }
+             }
+             class Bag {
+                 init() { this.items = [\"x\", \"y\"]; }
+                 iter() { return this.items.iter(); }
+             }
+             var out = [];
+             for (n in Countdown(3)) out.push(n);
+             for (item in Bag()) out.push(item);
+             fun first_over(xs, limit) {
+                 for (x in xs) if (x > limit) return x;
+                 return nil;
+             }
+             var found = first_over(0..100, 41);")
+        .unwrap();
+
+        assert_eq!(get(&interpreter, "out"), "[3, 2, 1, \"x\", \"y\"]");
+        assert_eq!(get(&interpreter, "found"), "42");
+
+        assert!(run("class A {} for (x in A()) print x;").is_err());
+    }
+
+    #[test]
+    fn handle_generators() {
+        let interpreter = run(
+            "fun naturals() { var n = 0; while (true) { yield n; n += 1; } }
+             fun take(gen, count) {
+                 var out = [];
+                 while (out.len() < count) out.push(gen.next());
+                 return out;
+             }
+             var first = take(naturals(), 4);
+             fun pairs(m) { for ((k, v) in m) if (v) yield (k, v); yield \"end\"; }
+             var seen = [];
+             for (p in pairs([(\"a\", 1), (\"b\", nil), (\"c\", 3)])) seen.push(p);
+             fun once() { yield 1; }
+             var g = once();
+             var results = [g.next(), g.next(), g.next()];
+             var log = [];
+             fun lazy() { log.push(\"started\"); yield 1; }
+             var l = lazy();
+             var before = log.len();
+             l.next();",
+        )
+        .unwrap();
+
+        assert_eq!(get(&interpreter, "first"), "[0, 1, 2, 3]");
+        assert_eq!(
+            get(&interpreter, "seen"),
+            "[(\"a\", 1), (\"c\", 3), \"end\"]"
+        );
+        assert_eq!(get(&interpreter, "results"), "[1, done, done]");
+        assert_eq!(get(&interpreter, "before"), "0");
+        assert_eq!(get(&interpreter, "log"), "[\"started\"]");
+        assert_eq!(get(&interpreter, "g"), "<generator once>");
+
+        assert!(run("fun bad() { yield 1 / nil; } bad().next();").is_err());
+        assert!(run("fun me() { yield g.next(); } var g = me(); g.next();").is_err());
+    }
+
+    #[test]
+    fn handle_comprehensions() {
+        let interpreter = run("var x = \"outer\";
+             var xs = [3, -1, 4, -1, 5];
+             var doubled = [x * 2 for x in xs if x > 0];
+             var pairs = [(\"a\", 1), (\"b\", 2)];
+             var swapped = {v: k for (k, v) in pairs};
+             var squares = {n: n * n for n in 1..=3 if n != 2};
+             var grid = [[i * j for j in 1..=2] for i in 1..=2];")
+        .unwrap();
+
+        assert_eq!(get(&interpreter, "doubled"), "[6, 8, 10]");
+        assert_eq!(get(&interpreter, "swapped"), "{1: \"a\", 2: \"b\"}");
+        assert_eq!(get(&interpreter, "squares"), "{1: 1, 3: 9}");
+        assert_eq!(get(&interpreter, "grid"), "[[1, 2], [2, 4]]");
+        // The loop variable doesn't leak into the enclosing scope.
+        assert_eq!(get(&interpreter, "x"), "outer");
+
+        assert!(run("var m = {[k]: 1 for k in 0..2};").is_err());
+        assert!(run("var l = [x for x in 5];").is_err());
+    }
+
+    #[test]
+    fn handle_lambdas() {
+        let interpreter = run("fun map_list(xs, f) { return [f(x) for x in xs]; }
+             var doubled = map_list([1, 2, 3], x => x * 2);
+             var summed = ((a, b) => a + b)(2, 3);
+             var piped = 4 |> (n => n * n);
+             fun counter() {
+                 var count = 0;
+                 return fun () { count += 1; return count; };
+             }
+             var next = counter();
+             next();
+             var counted = next();
+             var gen = fun () { yield 1; yield 2; };
+             var yielded = [x for x in gen()];
+             var empty = (() => nil)();
+             var f = x => x;")
+        .unwrap();
+
+        assert_eq!(get(&interpreter, "doubled"), "[2, 4, 6]");
+        assert_eq!(get(&interpreter, "summed"), "5");
+        assert_eq!(get(&interpreter, "piped"), "16");
+        assert_eq!(get(&interpreter, "counted"), "2");
+        assert_eq!(get(&interpreter, "yielded"), "[1, 2]");
+        assert_eq!(get(&interpreter, "empty"), "nil");
+        assert_eq!(get(&interpreter, "f"), "<fn lambda>");
+
+        assert!(run("var f = (a, b) => a; f(1);").is_err());
+    }
+
+    #[test]
+    fn handle_default_named_and_variadic_parameters() {
+        let interpreter = run(
+            "fun request(url, method = \"GET\", retries = 3, ...headers) {
+                 return (url, method, retries, headers);
+             }
+             var plain = request(\"/a\");
+             var named = request(\"/b\", retries: 5);
+             var all = request(\"/c\", \"POST\", 1, \"h1\", \"h2\");
+             fun scale(x, factor = x * 10) { return factor; }
+             var scaled = scale(2);
+             class Point { init(x = 0, y = 0) { this.x = x; this.y = y; } }
+             var p = Point(y: 4);
+             var sum = ((...xs) => xs.len())(1, 2, 3);",
+        )
+        .unwrap();
+
+        assert_eq!(get(&interpreter, "plain"), "(\"/a\", \"GET\", 3, [])");
+        assert_eq!(get(&interpreter, "named"), "(\"/b\", \"GET\", 5, [])");
+        assert_eq!(
+            get(&interpreter, "all"),
+            "(\"/c\", \"POST\", 1, [\"h1\", \"h2\"])"
+        );
+        assert_eq!(get(&interpreter, "scaled"), "20");
+        assert_eq!(get(&interpreter, "sum"), "3");
+
+        let error = |source: &str| run(source).err().unwrap();
+        assert_eq!(
+            error("fun f(a, b = 1) {}\nf(1, 2, 3);"),
+            "Expected 1 to 2 arguments but got 3 at line 2"
+        );
+        assert_eq!(
+            error("fun f(a, ...b) {} f();"),
+            "Expected at least 1 arguments but got 0 at line 1"
+        );
+        assert_eq!(
+            error("fun f(a) {}\nf(\nb: 1);"),
+            "Unknown argument 'b' for f at line 3"
+        );
+        assert_eq!(
+            error("fun f(a) {} f(1, a: 2);"),
+            "Got multiple values for argument 'a' at line 1"
+        );
+        assert_eq!(
+            error("fun f(a, b) {} f(b: 2);"),
+            "Missing argument 'a' for f at line 1"
+        );
+        assert!(run("class A {} A(x: 1);").is_err());
+        assert!(run("set(x: 1);").is_err());
+    }
+
+    #[test]
+    fn handle_break_and_continue() {
+        let interpreter = run("var odds = [];
+             for (var i = 0; i < 10; i += 1) {
+                 if (i % 2 == 0) continue;
+                 if (i > 7) break;
+                 odds.push(i);
+             }
+             var pairs = [];
+             outer: for (a in 1..=3) {
+                 for (b in 1..=3) {
+                     if (b == 2) continue outer;
+                     if (a == 3) break outer;
+                     pairs.push((a, b));
+                 }
+             }
+             var n = 0;
+             while (true) { n += 1; { if (n == 5) break; } }
+             fun find(xs, target) {
+                 var index = -1;
+                 for (var i = 0; i < xs.len(); i += 1) {
+                     if (xs[i] == target) { index = i; break; }
+                 }
+                 return index;
+             }
+             var found = find([4, 5, 6], 6);
+             fun evens() {
+                 for (var i = 0; ; i += 1) {
+                     if (i % 2 == 1) continue;
+                     if (i > 6) break;
+                     yield i;
+                 }
+             }
+             var yielded = [x for x in evens()];
+             var taken = [];
+             for (x in 0..100) { if (x == 3) break; taken.push(x); }")
+        .unwrap();
+
+        assert_eq!(get(&interpreter, "odds"), "[1, 3, 5, 7]");
+        assert_eq!(get(&interpreter, "pairs"), "[(1, 1), (2, 1)]");
+        assert_eq!(get(&interpreter, "n"), "5");
+        assert_eq!(get(&interpreter, "found"), "2");
+        assert_eq!(get(&interpreter, "yielded"), "[0, 2, 4, 6]");
+        assert_eq!(get(&interpreter, "taken"), "[0, 1, 2]");
+    }
+
+    #[test]
+    fn handle_exceptions() {
+        let interpreter = run("var log = [];
+             try { throw (\"custom\", 42); log.push(\"unreached\"); }
+             catch (e) { log.push(e); }
+             fun divide(a, b) {
+                 if (b == 0) throw \"division by zero\";
+                 return a / b;
+             }
+             try { divide(1, 0); } catch (e) { log.push(e); } finally { log.push(\"finally\"); }
+             var message; var kind; var line;
+             try {
+                 undefined_name;
+             } catch (e) { message = e.message; kind = e.kind; line = e.line; }
+             var index_kind;
+             try { [1][5]; } catch (e) { index_kind = e.kind; }
+             fun cleanup() {
+                 try { return \"returned\"; } finally { log.push(\"cleanup\"); }
+             }
+             var returned = cleanup();
+             for (x in 0..3) {
+                 try { if (x == 1) break; } finally { log.push(x); }
+             }
+             fun overridden() { try { throw 1; } finally { return \"finally wins\"; } }
+             var wins = overridden();
+             var rethrown;
+             try {
+                 try { throw \"inner\"; } catch (e) { throw e + \"!\"; }
+             } catch (e) { rethrown = e; }
+             fun guarded() {
+                 try { yield 1; throw \"stop\"; } catch (e) { yield e; } finally { yield \"done\"; }
+             }
+             var yielded = [x for x in guarded()];")
+        .unwrap();
+
+        assert_eq!(
+            get(&interpreter, "log"),
+            "[(\"custom\", 42), \"division by zero\", \"finally\", \"cleanup\", 0, 1]"
+        );
+        assert_eq!(
+            get(&interpreter, "message"),
+            "Undefined variable 'undefined_name'"
+        );
+        assert_eq!(get(&interpreter, "kind"), "NameError");
+        assert_eq!(get(&interpreter, "line"), "11");
+        assert_eq!(get(&interpreter, "index_kind"), "IndexError");
+        assert_eq!(get(&interpreter, "returned"), "returned");
+        assert_eq!(get(&interpreter, "wins"), "finally wins");
+        assert_eq!(get(&interpreter, "rethrown"), "inner!");
+        assert_eq!(get(&interpreter, "yielded"), "[1, \"stop\", \"done\"]");
+
+        assert_eq!(
+            run("\nthrow \"oops\";").err().unwrap(),
+            "Uncaught \"oops\" at line 2"
+        );
+        assert!(run("try { throw 1; } finally { }").is_err());
+    }
+
+    #[test]
+    fn handle_match_expressions() {
+        let interpreter = run("class Person { init(name) { this.name = name; } }
+             fun describe(value) {
+                 return match value {
+                     0 => \"zero\",
+                     -1 => \"minus one\",
+                     [] => \"empty\",
+                     [a, b] => a + b,
+                     [first, ...rest] => rest,
+                     (x, y) => x * y,
+                     {name: n} if n != \"\" => \"named \" + n,
+                     {name} => \"anonymous\",
+                     n if n > limit => \"big\",
+                     _ => \"other\"
+                 };
+             }
+             var limit = 100;
+             fun order(pair) {
+                 return match pair { (a, b) if (a < b) => \"lt\", (a, b) if a == b => \"eq\", _ => \"gt\" };
+             }
+             var orders = [order((1, 2)), order((2, 2)), order((3, 2))];
+             var results = [describe(v) for v in [0, -1, [], [1, 2], [1, 2, 3], (3, 4), {\"name\": \"Ada\"}, Person(\"\"), 500, 5]];
+             var kind;
+             var message;
+             try { match 3 { 1 => 1, 2 => 2 }; } catch (e) { kind = e.kind; message = e.message; }")
+        .unwrap();
+
+        assert_eq!(
+            get(&interpreter, "results"),
+            "[\"zero\", \"minus one\", \"empty\", 3, [2, 3], 12, \"named Ada\", \"anonymous\", \"big\", \"other\"]"
+        );
+        assert_eq!(get(&interpreter, "orders"), "[\"lt\", \"eq\", \"gt\"]");
+        assert_eq!(get(&interpreter, "kind"), "MatchError");
+        assert_eq!(get(&interpreter, "message"), "No pattern matched 3");
+        assert_eq!(
+            run("var x = [1];\nmatch x { [] => 0 };").err().unwrap(),
+            "No pattern matched [1] at line 2"
+        );
+    }
+
+    #[test]
+    fn handle_switch_statements() {
+        let interpreter = run("fun name(x) {
+                 var result = \"\";
+                 switch (x) {
+                     case 1:
+                         result = \"one\";
+                         break;
+                     case 2, 3:
+                         result = \"few\";
+                         break;
+                     case \"a\":
+                         result = result + \"a\";
+                     case \"b\":
+                         result = result + \"b\";
+                         break;
+                     default:
+                         result = \"many\";
+                 }
+                 return result;
+             }
+             var names = [name(x) for x in [1, 2, 3, \"a\", \"b\", 4]];
+             var count = 0;
+             for (x in 0..5) {
+                 switch (x % 2) {
+                     case 0: continue;
+                 }
+                 count += 1;
+             }
+             var unmatched = \"unchanged\";
+             switch (7) { case 1: unmatched = \"changed\"; }
+             fun cases() {
+                 for (x in [1, 2]) {
+                     switch (x) { case 1: yield \"one\"; yield \"still one\"; default: yield \"other\"; }
+                 }
+             }
+             var yielded = [x for x in cases()];")
+        .unwrap();
+
+        assert_eq!(
+            get(&interpreter, "names"),
+            "[\"one\", \"few\", \"few\", \"ab\", \"b\", \"many\"]"
+        );
+        assert_eq!(get(&interpreter, "count"), "2");
+        assert_eq!(get(&interpreter, "unmatched"), "unchanged");
+        assert_eq!(
+            get(&interpreter, "yielded"),
+            "[\"one\", \"still one\", \"other\", \"other\"]"
+        );
+    }
+
+    #[test]
+    fn handle_constants_and_freeze() {
+        let interpreter = run("const origin = (0, 0);
+             class Point { init(x) { this.x = x; } }
+             var point = freeze(Point(1));
+             var list = freeze([1, 2]);
+             var map = freeze({\"a\": 1});
+             var errors = [];
+             try { point.x = 2; } catch (e) { errors.push(e.message); }
+             try { list.push(3); } catch (e) { errors.push(e.message); }
+             try { list[0] = 3; } catch (e) { errors.push(e.message); }
+             try { map[\"b\"] = 2; } catch (e) { errors.push(e.message); }
+             try { map.remove(\"a\"); } catch (e) { errors.push(e.message); }
+             var copy = list[0:];
+             copy.push(3);
+             var frozen = [is_frozen(list), is_frozen(copy), is_frozen(1)];")
+        .unwrap();
+
+        assert_eq!(get(&interpreter, "origin"), "(0, 0)");
+        assert_eq!(
+            get(&interpreter, "errors"),
+            "[\"Cannot modify a frozen Point instance\", \"Cannot modify a frozen list\", \"Cannot modify a frozen list\", \"Cannot modify a frozen map\", \"Cannot modify a frozen map\"]"
+        );
+        assert_eq!(get(&interpreter, "list"), "[1, 2]");
+        assert_eq!(get(&interpreter, "copy"), "[1, 2, 3]");
+        assert_eq!(get(&interpreter, "frozen"), "[true, false, false]");
+        assert!(run("const a = 1; a = 2;").is_err());
+
+        let mut interpreter = run("fun reset() { limit = 0; }
+             const limit = 10;
+             var error = nil;
+             try { reset(); } catch (e) { error = (e.kind, e.message); }")
+        .unwrap();
+        assert_eq!(
+            get(&interpreter, "error"),
+            "(\"TypeError\", \"Can't assign to constant 'limit'\")"
+        );
+        assert_eq!(get(&interpreter, "limit"), "10");
+
+        let tokens = Scanner::new("limit = 11;").scan_tokens().unwrap();
+        let stmts = Parser::new(tokens).parse_program().unwrap();
+        assert!(interpreter.interpret(stmts).is_err());
+        assert_eq!(get(&interpreter, "limit"), "10");
+    }
+
+    #[test]
+    fn handle_enums() {
+        let interpreter = run("enum State { Loading, Loaded(data), Failed(error, retries) }
+             var states = [State.Loading, State.Loaded([1, 2]), State.Failed(\"timeout\", retries: 3)];
+             var printed = [s.to_string() for s in states];
+             var described = [match s {
+                 State.Loading => \"loading\",
+                 State.Loaded([first, ...rest]) => first,
+                 State.Failed(e, n) if n > 2 => \"gave up on \" + e,
+                 State.Failed => \"retrying\"
+             } for s in states];
+             var switched = [];
+             for (s in states) {
+                 switch (s) {
+                     case State.Loading: switched.push(\"loading\"); break;
+                     case State.Loaded, State.Failed: switched.push(\"finished\");
+                 }
+             }
+             var equal = [State.Loading == State.Loading, State.Loaded(1) == State.Loaded(1),
+                          State.Loaded(1) == State.Loaded(2), State.Loading == State.Loaded(nil)];
+             var counts = {State.Loading: 1, State.Loaded(1): 2};
+             var count = counts[State.Loaded(1)];
+             var data = states[1].data;
+             var error;
+             try { State.Loaded(); } catch (e) { error = e.message; }")
+        .unwrap();
+
+        assert_eq!(
+            get(&interpreter, "printed"),
+            "[\"State.Loading\", \"State.Loaded([1, 2])\", \"State.Failed(\"timeout\", 3)\"]"
+        );
+        assert_eq!(
+            get(&interpreter, "described"),
+            "[\"loading\", 1, \"gave up on timeout\"]"
+        );
+        assert_eq!(
+            get(&interpreter, "switched"),
+            "[\"loading\", \"finished\", \"finished\"]"
+        );
+        assert_eq!(get(&interpreter, "equal"), "[true, true, false, false]");
+        assert_eq!(get(&interpreter, "count"), "2");
+        assert_eq!(get(&interpreter, "data"), "[1, 2]");
+        assert_eq!(
+            get(&interpreter, "error"),
+            "Missing argument 'data' for Loaded"
+        );
+        assert!(run("enum A { X } A.Y;").is_err());
+    }
+
+    #[test]
+    fn handle_traits() {
+        let interpreter = run("trait Shape {
+                 area();
+                 describe() { return (this.name(), this.area()); }
+                 name() { return \"shape\"; }
+             }
+             trait Printable { show(); }
+             class Square impl Shape {
+                 init(side) { this.side = side; }
+                 area() { return this.side * this.side; }
+                 name() { return \"square\"; }
+             }
+             class Circle impl Shape, Printable {
+                 area() { return 3; }
+                 show() { return this.describe(); }
+             }
+             var square = Square(2);
+             var descriptions = [square.describe(), Circle().show()];
+             var checks = [square is Shape, square is Printable, square is Square,
+                           square is Circle, 1 is Shape, Circle() is Printable];
+             var error;
+             try {
+                 class Broken impl Shape, Printable { area() { return 0; } }
+             } catch (e) { error = e.message; }")
+        .unwrap();
+
+        assert_eq!(
+            get(&interpreter, "descriptions"),
+            "[(\"square\", 4), (\"shape\", 3)]"
+        );
+        assert_eq!(
+            get(&interpreter, "checks"),
+            "[true, false, true, false, false, true]"
+        );
+        assert_eq!(
+            get(&interpreter, "error"),
+            "Class Broken is missing method 'show' required by trait Printable"
+        );
+        assert!(run("var T = 1; class C impl T {}").is_err());
+        assert!(run("class C {} C() is 1;").is_err());
+    }
+
+    #[test]
+    fn handle_static_methods_and_accessors() {
+        let interpreter = run("class Rect {
+                 init(width, height) { this.width = width; this.height = height; }
+                 static square(side) { return this(side, side); }
+                 static describe() { return \"rectangles\"; }
+                 get area { return this.width * this.height; }
+                 get size { return (this.width, this.height); }
+                 set size(value) { (this.width, this.height) = value; }
+             }
+             var square = Rect.square(3);
+             var area = square.area;
+             square.size = (2, 5);
+             var resized = [square.width, square.height, square.area];
+             square.width += 1;
+             var grown = square.area;
+             var kind = Rect.describe();
+             var errors = [];
+             try { square.area = 1; } catch (e) { errors.push(e.kind); }
+             try { Rect.missing(); } catch (e) { errors.push(e.kind); }
+             class Checked {
+                 get value { throw \"no value\"; }
+             }
+             try { Checked().value; } catch (e) { errors.push(e); }")
+        .unwrap();
+
+        assert_eq!(get(&interpreter, "area"), "9");
+        assert_eq!(get(&interpreter, "resized"), "[2, 5, 10]");
+        assert_eq!(get(&interpreter, "grown"), "15");
+        assert_eq!(get(&interpreter, "kind"), "rectangles");
+        assert_eq!(
+            get(&interpreter, "errors"),
+            "[\"PropertyError\", \"PropertyError\", \"no value\"]"
+        );
+    }
+
+    #[test]
+    fn handle_operator_overloading() {
+        let interpreter = run("class Vector {
+                 init(x, y) { this.x = x; this.y = y; }
+                 __add__(other) { return Vector(this.x + other.x, this.y + other.y); }
+                 __mul__(factor) { return Vector(this.x * factor, this.y * factor); }
+                 __neg__() { return Vector(-this.x, -this.y); }
+                 __eq__(other) {
+                     if (!(other is Vector)) return false;
+                     return this.x == other.x ? this.y == other.y : false;
+                 }
+                 __lt__(other) { return this.length() < other.length(); }
+                 __gt__(other) { return this.length() > other.length(); }
+                 __index__(i) { return [this.x, this.y][i]; }
+                 __setindex__(i, value) { if (i == 0) this.x = value; else this.y = value; }
+                 __str__() { return \"Vector\"; }
+                 length() { return this.x * this.x + this.y * this.y; }
+             }
+             var a = Vector(1, 2);
+             var b = Vector(3, 4);
+             var sum = a + b;
+             var summed = [sum.x, sum.y];
+             var scaled = (a * 3)[1];
+             var negated = (-a)[0];
+             var comparisons = [a == Vector(1, 2), a != b, a < b, a > b, a == 1, 1 == a];
+             var nested = [[a] == [Vector(1, 2)], (a, 1) == (Vector(1, 2), 1),
+                 {\"v\": a} == {\"v\": Vector(1, 2)}, [a] != [Vector(1, 2)], [a] == [b]];
+             enum Shape { Arrow(direction) }
+             var found;
+             switch (Vector(1, 2)) { case a: found = \"case\"; break; default: found = \"default\"; }
+             var elsewhere = [[b, a].contains(Vector(1, 2)), found, Shape.Arrow(a) == Shape.Arrow(Vector(1, 2)),
+                 match Vector(1, 2) { Shape.Arrow(_) => \"arrow\", _ => \"other\" }];
+             a += b;
+             var added = [a[0], a[1]];
+             a[1] = 10;
+             a[0] += 5;
+             var stored = [a.x, a.y];
+             var shown = [a, (b, \"b\"), {\"a\": a}];
+             print a;
+             var errors = [];
+             try { a - b; } catch (e) { errors.push(e.kind); }
+             class Bad { __str__() { return 1; } }
+             try { print Bad(); } catch (e) { errors.push(e.kind); }")
+        .unwrap();
+
+        assert_eq!(get(&interpreter, "summed"), "[4, 6]");
+        assert_eq!(get(&interpreter, "scaled"), "6");
+        assert_eq!(get(&interpreter, "negated"), "-1");
+        assert_eq!(
+            get(&interpreter, "comparisons"),
+            "[true, true, true, false, false, false]"
+        );
+        assert_eq!(
+            get(&interpreter, "nested"),
+            "[true, true, true, false, false]"
+        );
+        assert_eq!(
+            get(&interpreter, "elsewhere"),
+            "[true, \"case\", true, \"other\"]"
+        );
+        assert_eq!(get(&interpreter, "added"), "[4, 6]");
+        assert_eq!(get(&interpreter, "stored"), "[9, 10]");
+        assert_eq!(
+            get(&interpreter, "errors"),
+            "[\"TypeError\", \"TypeError\"]"
+        );
+        let shown = interpreter.environment.borrow().get("shown").unwrap();
+        let keyword = Token::new(
+            crate::scanner::TokenType::Print,
+            "print".to_string(),
+            None,
+            1,
+        );
+        assert_eq!(
+            expr::display(&shown, &keyword).unwrap(),
+            "[Vector, (Vector, \"b\"), {\"a\": Vector}]"
+        );
+        assert_eq!(
+            run("class Bad { __str__() { return 1; } }\nprint {\"k\": Bad()};")
+                .err()
+                .unwrap(),
+            "__str__ must return a string, got 1 at line 2"
+        );
+    }
+
+    #[test]
+    fn handle_modules() {
+        let directory =
+            std::env::temp_dir().join(format!("kinglang_modules_{}", std::process::id()));
+        std::fs::create_dir_all(directory.join("lib")).unwrap();
+        let files = [
+            (
+                "lib/math.king",
+                "export var loads = 0;
+                 loads += 1;
+                 export const PI = 3;
+                 export fun square(x) { return x * x; }
+                 export var count = 0;
+                 export fun bump() { count += 1; }
+                 var hidden = 1;",
+            ),
+            (
+                "lib/shapes.king",
+                "from \"math.king\" import PI, square;
+                 export fun area(r) { return PI * square(r); }",
+            ),
+            ("lib/broken.king", "export var a = 1; a.b;"),
+            ("lib/throws.king", "var a = 1;\n\n\nthrow \"bad module\";"),
+            ("lib/syntax.king", "var = 1;"),
+            ("lib/nested.king", "\n import \"broken.king\" as broken;"),
+            ("cycle_a.king", "import \"cycle_b.king\" as b;"),
+            ("cycle_b.king", "import \"lib/cycle_c.king\" as c;"),
+            ("lib/cycle_c.king", "\n import \"../cycle_b.king\" as b;"),
+            (
+                "main.king",
+                "import \"lib/math.king\" as math;
+                 import \"./lib/math.king\" as again;
+                 from \"lib/shapes.king\" import area;
+                 var squared = math.square(4);
+                 var circle = area(2);
+                 math.bump();
+                 math.bump();
+                 var state = (math.count, math.loads, math == again);
+                 from \"lib/math.king\" import PI;
+                 var errors = [];
+                 try { math.hidden; } catch (e) { errors.push(e.kind); }
+                 try { PI = 4; } catch (e) { errors.push(e.kind); }",
+            ),
+        ];
+        for (name, source) in files {
+            std::fs::write(directory.join(name), source).unwrap();
+        }
+
+        let run_file = |name: &str| -> Result<Interpreter, String> {
+            let path = directory.join(name);
+            let source = std::fs::read_to_string(&path).unwrap();
+            let tokens = Scanner::new(&source).scan_tokens()?;
+            let stmts = Parser::new(tokens).parse_program()?;
+            let mut interpreter = Interpreter::for_file(&path);
+            interpreter.interpret(stmts)?;
+            Ok(interpreter)
+        };
+        let interpreter = run_file("main.king").unwrap();
+        let cycle = run_file("cycle_a.king").err().unwrap();
+        let canonical = std::fs::canonicalize(&directory).unwrap();
+        let lib = directory.join("lib").display().to_string();
+        let errors = [
+            format!("from \"{}/math.king\" import hidden;", lib),
+            format!("import \"{}/missing.king\" as missing;", lib),
+            format!("import \"{}/broken.king\" as broken;", lib),
+            format!("import \"{}/throws.king\" as throws;", lib),
+            format!("import \"{}/nested.king\" as nested;", lib),
+            format!("\n\nimport \"{}/syntax.king\" as syntax;", lib),
+        ]
+        .map(|source| run(&source).err().unwrap());
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(get(&interpreter, "squared"), "16");
+        assert_eq!(get(&interpreter, "circle"), "12");
+        assert_eq!(get(&interpreter, "state"), "(2, 1, true)");
+        assert_eq!(
+            get(&interpreter, "errors"),
+            "[\"PropertyError\", \"TypeError\"]"
+        );
+        assert_eq!(get(&interpreter, "PI"), "3");
+        assert_eq!(
+            cycle,
+            format!(
+                "Cyclic import of '../cycle_b.king' ({0}/cycle_b.king -> {0}/lib/cycle_c.king \
+                 -> {0}/cycle_b.king) at line 2 in module 'lib/cycle_c.king'",
+                canonical.display()
+            )
+        );
+        assert!(errors[0].starts_with("Module"));
+        assert!(errors[1].starts_with("Cannot import"));
+        assert_eq!(
+            errors[2],
+            format!(
+                "Only instances have properties, got 1 at line 1 in module '{}/broken.king'",
+                lib
+            )
+        );
+        assert_eq!(
+            errors[3],
+            format!(
+                "Uncaught \"bad module\" at line 4 in module '{}/throws.king'",
+                lib
+            )
+        );
+        assert_eq!(
+            errors[4],
+            "Only instances have properties, got 1 at line 1 in module 'broken.king'"
+        );
+        assert_eq!(
+            errors[5],
+            format!(
+                "Error in module '{}/syntax.king':\nExpected variable name at line 1 at line 3",
+                lib
+            )
+        );
+    }
+
+    #[test]
+    fn handle_recursion_limit() {
+        let program = std::thread::Builder::new()
+            .stack_size(crate::function::STACK_SIZE)
+            .spawn(|| {
+                let interpreter = run(
+                    "fun depth(n) { if (n == 0) return 0; return depth(n - 1) + 1; }
+                     fun forever() { return forever(); }
+                     class Point { set x(value) { this.x = value; } }
+                     var errors = [];
+                     try { forever(); } catch (e) { errors.push(e.kind); }
+                     try { Point().x = 1; } catch (e) { errors.push(e.kind); }
+                     var deep = depth(900);",
+                )
+                .unwrap();
+                (get(&interpreter, "errors"), get(&interpreter, "deep"))
+            })
+            .unwrap();
+
+        let (errors, deep) = program.join().unwrap();
+        assert_eq!(errors, "[\"RecursionError\", \"RecursionError\"]");
+        assert_eq!(deep, "900");
+    }
+
+    #[test]
+    fn handle_cyclic_containers() {
+        let interpreter = run("var xs = [1]; xs.push(xs);
+             var ys = [1]; ys.push(ys);
+             var m = {\"a\": 1}; m[\"self\"] = m;
+             var n = {\"a\": 1}; n[\"self\"] = n;
+             var nested = [m, (xs,)];
+             var equal = (xs == ys, m == n, xs == [1, [1]]);")
+        .unwrap();
+
+        assert_eq!(get(&interpreter, "xs"), "[1, [...]]");
+        assert_eq!(get(&interpreter, "m"), "{\"a\": 1, \"self\": {...}}");
+        assert_eq!(
+            get(&interpreter, "nested"),
+            "[{\"a\": 1, \"self\": {...}}, ([1, [...]],)]"
+        );
+        assert_eq!(get(&interpreter, "equal"), "(true, true, false)");
+    }
+
+    #[test]
+    fn handle_invalid_assignment_targets() {
+        assert!(run("var a = 1; 1 += a;").is_err());
+        assert!(run("var a = 1; (a)++;").is_err());
+        assert!(run("undefined_var += 1;").is_err());
+        assert!(run("var s = \"a\"; s++;").is_err());
+    }
+}
@@ -1,6 +1,27 @@
+#![allow(
+    clippy::enum_variant_names,
+    clippy::inherent_to_string,
+    clippy::needless_arbitrary_self_type
+)]
+
+mod builtins;
+mod class;
+mod enums;
+mod environment;
+mod error;
 mod expr;
+mod function;
+mod generator;
+mod interpreter;
+mod map;
+mod module;
 mod parser;
+mod pattern;
+mod range;
 mod scanner;
+mod stmt;
+mod traits;
+use interpreter::Interpreter;
 use parser::Parser;
 
 use crate::scanner::*;
@@ -8,29 +29,32 @@ use crate::scanner::*;
 use std::{
     env, fs,
     io::{self, BufRead, Write},
+    path::Path,
     process::exit,
+    thread,
 };
 
 fn run_file(path: &str) -> Result<(), String> {
+    let mut interpreter = Interpreter::for_file(Path::new(path));
     match fs::read_to_string(path) {
-        Err(msg) => return Err(msg.to_string()),
-        Ok(contents) => return run(&contents),
+        Err(msg) => Err(msg.to_string()),
+        Ok(contents) => run(&mut interpreter, &contents),
     }
 }
 
-fn run(contents: &str) -> Result<(), String> {
+fn run(interpreter: &mut Interpreter, contents: &str) -> Result<(), String> {
     let mut scanner = Scanner::new(contents);
     let tokens = scanner.scan_tokens()?;
 
     let mut parser = Parser::new(tokens);
-    let expr = parser.parse()?;
-    let result = expr.evaluate()?;
-    println!("{}", result.to_string());
+    let stmts = parser.parse_program()?;
+    interpreter.interpret(stmts)?;
 
     Ok(())
 }
 
 fn run_prompt() -> Result<(), String> {
+    let mut interpreter = Interpreter::new();
     loop {
         println!("> ");
         let mut buffer = String::new();
@@ -49,7 +73,7 @@ fn run_prompt() -> Result<(), String> {
             Err(_) => return Err("Could not read line".to_string()),
         }
         println!("ECHO: {}", buffer);
-        match run(&buffer) {
+        match run(&mut interpreter, &buffer) {
             Ok(_) => (),
             Err(msg) => println!("{}", msg),
         }
@@ -57,6 +81,18 @@ fn run_prompt() -> Result<(), String> {
 }
 
 fn main() {
+    // Calls in a script nest on the native stack, so the program runs on a
+    // thread with room for as many of them as the interpreter allows.
+    let program = thread::Builder::new()
+        .stack_size(function::STACK_SIZE)
+        .spawn(start)
+        .expect("Could not start the interpreter thread");
+    if program.join().is_err() {
+        exit(70);
+    }
+}
+
+fn start() {
     let args: Vec<String> = env::args().collect();
 
     if args.len() > 2 {
@@ -0,0 +1,191 @@
+use crate::expr::LiteralValue;
+use std::{collections::HashMap, rc::Rc};
+
+/// The hashable projection of a `LiteralValue` used to look up map keys.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HashKey {
+    Number(u32),
+    StringValue(String),
+    Bool(bool),
+    Nil,
+    Tuple(Vec<HashKey>),
+    /// An enum value, by the address of its enum, its variant and its fields.
+    EnumValue(usize, usize, Vec<HashKey>),
+}
+
+impl HashKey {
+    pub fn from_value(value: &LiteralValue) -> Result<Self, String> {
+        match value {
+            LiteralValue::Number(x) if x.is_nan() => Err("NaN cannot be used as a key".to_string()),
+            // -0.0 == 0.0, so both must hash the same.
+            LiteralValue::Number(x) if *x == 0.0 => Ok(HashKey::Number(0.0f32.to_bits())),
+            LiteralValue::Number(x) => Ok(HashKey::Number(x.to_bits())),
+            LiteralValue::StringValue(s) => Ok(HashKey::StringValue(s.clone())),
+            LiteralValue::True => Ok(HashKey::Bool(true)),
+            LiteralValue::False => Ok(HashKey::Bool(false)),
+            LiteralValue::Nil => Ok(HashKey::Nil),
+            LiteralValue::Tuple(items) => Ok(HashKey::Tuple(
+                items
+                    .iter()
+                    .map(HashKey::from_value)
+                    .collect::<Result<Vec<HashKey>, String>>()?,
+            )),
+            LiteralValue::EnumValue(value) => Ok(HashKey::EnumValue(
+                Rc::as_ptr(&value.enum_type) as usize,
+                value.variant,
+                value
+                    .values
+                    .iter()
+                    .map(HashKey::from_value)
+                    .collect::<Result<Vec<HashKey>, String>>()?,
+            )),
+            _ => Err(format!("Unhashable key {}", value.to_string())),
+        }
+    }
+}
+
+/// A hash map that remembers insertion order.
+#[derive(Debug, Clone)]
+pub struct Map {
+    entries: Vec<(LiteralValue, LiteralValue)>,
+    indices: HashMap<HashKey, usize>,
+}
+
+impl Map {
+    pub fn new() -> Self {
+        Self {
+            entries: vec![],
+            indices: HashMap::new(),
+        }
+    }
+
+    pub fn len(self: &Self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(self: &Self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(self: &Self, key: &LiteralValue) -> Result<Option<LiteralValue>, String> {
+        let hash_key = HashKey::from_value(key)?;
+        Ok(self
+            .indices
+            .get(&hash_key)
+            .map(|&i| self.entries[i].1.clone()))
+    }
+
+    pub fn insert(self: &mut Self, key: LiteralValue, value: LiteralValue) -> Result<(), String> {
+        let hash_key = HashKey::from_value(&key)?;
+        match self.indices.get(&hash_key) {
+            Some(&i) => self.entries[i].1 = value,
+            None => {
+                self.indices.insert(hash_key, self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn remove(self: &mut Self, key: &LiteralValue) -> Result<Option<LiteralValue>, String> {
+        let hash_key = HashKey::from_value(key)?;
+        let i = match self.indices.remove(&hash_key) {
+            Some(i) => i,
+            None => return Ok(None),
+        };
+
+        let (_, value) = self.entries.remove(i);
+        for index in self.indices.values_mut() {
+            if *index > i {
+                *index -= 1;
+            }
+        }
+
+        Ok(Some(value))
+    }
+
+    pub fn entries(self: &Self) -> &[(LiteralValue, LiteralValue)] {
+        &self.entries
+    }
+}
+
+/// A set of hashable values that iterates in insertion order.
+#[derive(Debug, Clone)]
+pub struct Set {
+    map: Map,
+}
+
+impl Set {
+    pub fn new() -> Self {
+        Self { map: Map::new() }
+    }
+
+    pub fn from_items(items: Vec<LiteralValue>) -> Result<Self, String> {
+        let mut set = Set::new();
+        for item in items {
+            set.insert(item)?;
+        }
+
+        Ok(set)
+    }
+
+    pub fn len(self: &Self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(self: &Self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn contains(self: &Self, item: &LiteralValue) -> Result<bool, String> {
+        Ok(self.map.get(item)?.is_some())
+    }
+
+    pub fn insert(self: &mut Self, item: LiteralValue) -> Result<(), String> {
+        self.map.insert(item, LiteralValue::Nil)
+    }
+
+    pub fn remove(self: &mut Self, item: &LiteralValue) -> Result<bool, String> {
+        Ok(self.map.remove(item)?.is_some())
+    }
+
+    pub fn items(self: &Self) -> Vec<LiteralValue> {
+        self.map
+            .entries()
+            .iter()
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    pub fn union(self: &Self, other: &Set) -> Result<Set, String> {
+        let mut result = self.clone();
+        for item in other.items() {
+            result.insert(item)?;
+        }
+
+        Ok(result)
+    }
+
+    pub fn intersection(self: &Self, other: &Set) -> Result<Set, String> {
+        let mut result = Set::new();
+        for item in self.items() {
+            if other.contains(&item)? {
+                result.insert(item)?;
+            }
+        }
+
+        Ok(result)
+    }
+
+    pub fn difference(self: &Self, other: &Set) -> Result<Set, String> {
+        let mut result = Set::new();
+        for item in self.items() {
+            if !other.contains(&item)? {
+                result.insert(item)?;
+            }
+        }
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,156 @@
+use crate::environment::Environment;
+use crate::error::RuntimeError;
+use crate::expr::LiteralValue;
+use crate::interpreter::Interpreter;
+use crate::parser::Parser;
+use crate::scanner::{Scanner, Token};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+/// A file loaded by `import`, with the names it declared with `export`.
+pub struct Module {
+    pub path: String,
+    pub environment: Rc<RefCell<Environment>>,
+    pub exports: Vec<String>,
+}
+
+impl Module {
+    /// The current value of an exported name. Reads go through the module's
+    /// environment, so they see later assignments made inside the module.
+    pub fn export(self: &Self, name: &str) -> Option<LiteralValue> {
+        if !self.exports.iter().any(|export| export == name) {
+            return None;
+        }
+
+        self.environment.borrow().get(name)
+    }
+
+    /// Looks up `module.name`, which must be exported.
+    pub fn get(module: &Rc<Module>, name: &Token) -> Result<LiteralValue, String> {
+        module
+            .export(&name.lexeme)
+            .ok_or_else(|| format!("Module '{}' has no export '{}'", module.path, name.lexeme))
+    }
+}
+
+impl std::fmt::Debug for Module {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "<module {}>", self.path)
+    }
+}
+
+/// The modules of a program, shared by the interpreters running its files,
+/// so that each file executes once however many times it is imported.
+pub struct Modules {
+    loaded: HashMap<PathBuf, Rc<Module>>,
+    /// The files being executed, outermost first. Importing one of them
+    /// again is a cycle.
+    loading: Vec<PathBuf>,
+}
+
+impl Modules {
+    pub fn new() -> Self {
+        Self {
+            loaded: HashMap::new(),
+            loading: vec![],
+        }
+    }
+
+    /// Records the main script as being executed, so that modules importing
+    /// it back are reported as a cycle.
+    pub fn enter(self: &mut Self, path: &Path) {
+        if let Ok(path) = fs::canonicalize(path) {
+            self.loading.push(path);
+        }
+    }
+
+    /// Loads the module at `path`, relative to `directory`, executing it
+    /// first unless it was already loaded.
+    pub fn load(
+        modules: &Rc<RefCell<Modules>>,
+        directory: &Path,
+        path: &str,
+        keyword: &Token,
+    ) -> Result<Rc<Module>, RuntimeError> {
+        let import_error = |message: String| RuntimeError::new("ImportError", message).at(keyword);
+
+        let resolved = fs::canonicalize(directory.join(path))
+            .map_err(|error| import_error(format!("Cannot import '{}': {}", path, error)))?;
+        if let Some(module) = modules.borrow().loaded.get(&resolved) {
+            return Ok(module.clone());
+        }
+        if let Some(start) = modules
+            .borrow()
+            .loading
+            .iter()
+            .position(|loading| *loading == resolved)
+        {
+            let cycle = modules.borrow().loading[start..]
+                .iter()
+                .chain([&resolved])
+                .map(|file| file.display().to_string())
+                .collect::<Vec<String>>()
+                .join(" -> ");
+            return Err(import_error(format!(
+                "Cyclic import of '{}' ({})",
+                path, cycle
+            )));
+        }
+
+        let contents = fs::read_to_string(&resolved)
+            .map_err(|error| import_error(format!("Cannot import '{}': {}", path, error)))?;
+        let stmts = Scanner::new(&contents)
+            .scan_tokens()
+            .and_then(|tokens| Parser::new(tokens).parse_program())
+            .map_err(|error| import_error(format!("Error in module '{}':\n{}", path, error)))?;
+
+        let directory = resolved.parent().unwrap_or(Path::new(".")).to_path_buf();
+        let mut interpreter = Interpreter::for_module(modules.clone(), directory);
+        modules.borrow_mut().loading.push(resolved.clone());
+        let result = interpreter.execute_module(&stmts);
+        modules.borrow_mut().loading.pop();
+
+        // Errors keep their kind, so that the importer can catch them as it
+        // would the same error in its own code, and thrown values stay as
+        // they were thrown. The message is given the module the error
+        // happened in, which also takes the place of its line, so that
+        // modules further out pass it on unchanged.
+        if let Err(error) = result {
+            return Err(match error {
+                RuntimeError::Error {
+                    kind,
+                    message,
+                    line: Some(line),
+                } => RuntimeError::Error {
+                    kind,
+                    message: format!("{} at line {} in module '{}'", message, line, path),
+                    line: None,
+                },
+                RuntimeError::Thrown {
+                    value,
+                    line,
+                    module: None,
+                } => RuntimeError::Thrown {
+                    value,
+                    line,
+                    module: Some(path.to_string()),
+                },
+                error => error,
+            });
+        }
+
+        let module = Rc::new(Module {
+            path: path.to_string(),
+            environment: interpreter.environment(),
+            exports: interpreter.take_exports(),
+        });
+        modules.borrow_mut().loaded.insert(resolved, module.clone());
+
+        Ok(module)
+    }
+}
@@ -1,34 +1,1455 @@
 use crate::expr::{Expr, Expr::*, LiteralValue};
+use crate::pattern::{MatchArm, Pattern};
 use crate::scanner::{Token, TokenType, TokenType::*};
+use crate::stmt::{Param, Stmt, SwitchCase};
+use std::{collections::HashMap, rc::Rc};
+
+/// The methods, static methods, getters and setters of a class body.
+type ClassMembers = (Vec<Stmt>, Vec<Stmt>, Vec<Stmt>, Vec<Stmt>);
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    function_depth: usize,
+    class_depth: usize,
+    yield_found: bool,
+    /// Labels of the loops enclosing the current statement, innermost last.
+    loops: Vec<Option<Token>>,
+    /// How many switch statements enclose the current statement, for `break`.
+    switch_depth: usize,
+    /// The names declared in each enclosing scope, innermost last, mapped to
+    /// the declaring token for constants.
+    scopes: Vec<HashMap<String, Option<Token>>>,
+    /// The errors found so far, in the order they were found.
+    errors: Vec<String>,
+    /// Whether a `=>` ends the expression being parsed, as after a match
+    /// guard, instead of starting an arrow function.
+    no_arrow: bool,
 }
 
-macro_rules! match_tokens {
-    ($parser:ident, $($token:ident),+) => {
+impl Parser {
+    pub fn new(tokens: Vec<Token>) -> Self {
+        Self {
+            tokens,
+            current: 0,
+            function_depth: 0,
+            class_depth: 0,
+            yield_found: false,
+            loops: vec![],
+            switch_depth: 0,
+            scopes: vec![HashMap::new()],
+            errors: vec![],
+            no_arrow: false,
+        }
+    }
+
+    pub fn parse_program(self: &mut Self) -> Result<Vec<Stmt>, String> {
+        let mut stmts = vec![];
+
+        while !self.is_at_end() {
+            match self.declaration() {
+                Ok(stmt) => stmts.push(stmt),
+                Err(msg) => {
+                    self.errors.push(msg);
+                    self.synchronize();
+                }
+            }
+        }
+
+        if !self.errors.is_empty() {
+            return Err(self.errors.join("\n"));
+        }
+
+        Ok(stmts)
+    }
+
+    fn declaration(self: &mut Self) -> Result<Stmt, String> {
+        if self.match_token(Var) {
+            self.var_declaration()
+        } else if self.match_token(Const) {
+            self.const_declaration()
+        } else if self.check(Fun) && self.check_next(Identifier) {
+            self.advance();
+            self.function("function")
+        } else if self.match_token(Class) {
+            self.class_declaration()
+        } else if self.match_token(TokenType::Trait) {
+            self.trait_declaration()
+        } else if self.match_token(TokenType::Enum) {
+            self.enum_declaration()
+        } else if self.match_token(Import) {
+            self.import_declaration()
+        } else if self.check(Identifier)
+            && self.peek().lexeme == "from"
+            && self.check_next(StringKing)
         {
-            let mut result = false;
+            self.advance();
+            self.from_import_declaration()
+        } else if self.match_token(Export) {
+            self.export_declaration()
+        } else {
+            self.statement()
+        }
+    }
+
+    /// Reports the statement starting with `keyword` unless it is at the top
+    /// level of the file, where imports and exports must be.
+    fn check_top_level(self: &mut Self, keyword: &Token) {
+        if self.scopes.len() > 1 || self.switch_depth > 0 {
+            self.report(format!(
+                "'{}' must be at the top level of a file at line {}",
+                keyword.lexeme, keyword.line_number
+            ));
+        }
+    }
+
+    fn module_path(self: &mut Self) -> Result<String, String> {
+        let path = self.consume(StringKing, "Expected module path string")?;
+        match LiteralValue::from_token(path) {
+            LiteralValue::StringValue(path) => Ok(path),
+            _ => unreachable!(),
+        }
+    }
+
+    fn import_declaration(self: &mut Self) -> Result<Stmt, String> {
+        let keyword = self.previous();
+        self.check_top_level(&keyword);
+        let path = self.module_path()?;
+
+        if !(self.check(Identifier) && self.peek().lexeme == "as") {
+            return Err(format!(
+                "Expected 'as' after module path at line {}",
+                self.peek().line_number
+            ));
+        }
+        self.advance();
+        let alias = self.consume(Identifier, "Expected module name after 'as'")?;
+        self.declare(&alias, false);
+        self.consume(Semicolon, "Expected ';' after import")?;
+
+        Ok(Stmt::Import {
+            keyword,
+            path,
+            alias: Some(alias),
+            names: vec![],
+        })
+    }
+
+    fn from_import_declaration(self: &mut Self) -> Result<Stmt, String> {
+        let keyword = self.previous();
+        self.check_top_level(&keyword);
+        let path = self.module_path()?;
+        self.consume(Import, "Expected 'import' after module path")?;
+
+        let mut names: Vec<Token> = vec![];
+        loop {
+            let name = self.consume(Identifier, "Expected name to import")?;
+            if names.iter().any(|other| other.lexeme == name.lexeme) {
+                return Err(format!(
+                    "Duplicate import '{}' at line {}",
+                    name.lexeme, name.line_number
+                ));
+            }
+            self.declare(&name, false);
+            names.push(name);
+
+            if !self.match_token(Comma) {
+                break;
+            }
+        }
+        self.consume(Semicolon, "Expected ';' after import")?;
+
+        Ok(Stmt::Import {
+            keyword,
+            path,
+            alias: None,
+            names,
+        })
+    }
+
+    fn export_declaration(self: &mut Self) -> Result<Stmt, String> {
+        let keyword = self.previous();
+        self.check_top_level(&keyword);
+
+        let declaration = self.declaration()?;
+        if declaration.declared_names().is_empty() {
+            return Err(format!(
+                "Expected a declaration after 'export' at line {}",
+                keyword.line_number
+            ));
+        }
+
+        Ok(Stmt::Export {
+            keyword,
+            declaration: Box::new(declaration),
+        })
+    }
+
+    fn class_declaration(self: &mut Self) -> Result<Stmt, String> {
+        let name = self.consume(Identifier, "Expected class name")?;
+        self.declare(&name, false);
+
+        let mut traits = vec![];
+        if self.match_token(Impl) {
+            loop {
+                traits.push(self.consume(Identifier, "Expected trait name after 'impl'")?);
+
+                if !self.match_token(Comma) {
+                    break;
+                }
+            }
+        }
+        self.consume(LeftBrace, "Expected '{' before class body")?;
+
+        self.class_depth += 1;
+        let members = self.class_members();
+        self.class_depth -= 1;
+        let (methods, statics, getters, setters) = members?;
+
+        self.consume(RightBrace, "Expected '}' after class body")?;
+
+        Ok(Stmt::Class {
+            name,
+            traits,
+            methods,
+            statics,
+            getters,
+            setters,
+        })
+    }
+
+    /// Parses the methods of a class body, along with `static` methods and
+    /// `get name { ... }` and `set name(value) { ... }` accessors.
+    fn class_members(self: &mut Self) -> Result<ClassMembers, String> {
+        let (mut methods, mut statics, mut getters, mut setters) = (vec![], vec![], vec![], vec![]);
+
+        while !self.check(RightBrace) && !self.is_at_end() {
+            let modifier = self.peek();
+            if !self.check_next(Identifier) {
+                methods.push(self.function("method")?);
+                continue;
+            }
+
+            match modifier.lexeme.as_str() {
+                "static" => {
+                    self.advance();
+                    statics.push(self.function("static method")?);
+                }
+                "get" => {
+                    self.advance();
+                    let name = self.consume(Identifier, "Expected getter name")?;
+                    self.consume(LeftBrace, "Expected '{' after getter name")?;
+                    let (body, is_generator) = self.function_body(&[], |parser| parser.block())?;
+                    getters.push(Stmt::Function {
+                        name,
+                        params: Rc::new(vec![]),
+                        body: Rc::new(body),
+                        is_generator,
+                    });
+                }
+                "set" => {
+                    self.advance();
+                    let setter = self.function("setter")?;
+                    if let Stmt::Function { name, params, .. } = &setter {
+                        if params.len() != 1 || params[0].default.is_some() || params[0].variadic {
+                            return Err(format!(
+                                "Setter '{}' must take exactly one parameter at line {}",
+                                name.lexeme, name.line_number
+                            ));
+                        }
+                    }
+                    setters.push(setter);
+                }
+                _ => {
+                    return Err(format!(
+                        "Unknown modifier '{}' at line {}",
+                        modifier.lexeme, modifier.line_number
+                    ))
+                }
+            }
+        }
+
+        Ok((methods, statics, getters, setters))
+    }
+
+    /// Parses the body of a trait: required methods end in ';' after their
+    /// parameters, while default methods have a body.
+    fn trait_declaration(self: &mut Self) -> Result<Stmt, String> {
+        let name = self.consume(Identifier, "Expected trait name")?;
+        self.declare(&name, false);
+        self.consume(LeftBrace, "Expected '{' before trait body")?;
+
+        self.class_depth += 1;
+        let members = self.trait_members();
+        self.class_depth -= 1;
+        let (required, methods) = members?;
+        self.consume(RightBrace, "Expected '}' after trait body")?;
+
+        Ok(Stmt::Trait {
+            name,
+            required,
+            methods,
+        })
+    }
+
+    fn trait_members(self: &mut Self) -> Result<(Vec<Token>, Vec<Stmt>), String> {
+        let mut names: Vec<Token> = vec![];
+        let mut required = vec![];
+        let mut methods = vec![];
+
+        while !self.check(RightBrace) && !self.is_at_end() {
+            let name = self.consume(Identifier, "Expected method name")?;
+            if names.iter().any(|other| other.lexeme == name.lexeme) {
+                return Err(format!(
+                    "Duplicate method '{}' in trait at line {}",
+                    name.lexeme, name.line_number
+                ));
+            }
+            names.push(name.clone());
+
+            self.consume(LeftParen, "Expected '(' after method name")?;
+            let params = self.parameters()?;
+            if self.match_token(Semicolon) {
+                required.push(name);
+                continue;
+            }
+
+            self.consume(LeftBrace, "Expected '{' or ';' after method parameters")?;
+            let (body, is_generator) = self.function_body(&params, |parser| parser.block())?;
+            methods.push(Stmt::Function {
+                name,
+                params: Rc::new(params),
+                body: Rc::new(body),
+                is_generator,
+            });
+        }
+
+        Ok((required, methods))
+    }
+
+    fn enum_declaration(self: &mut Self) -> Result<Stmt, String> {
+        let name = self.consume(Identifier, "Expected enum name")?;
+        self.declare(&name, false);
+        self.consume(LeftBrace, "Expected '{' before enum body")?;
+
+        let mut variants: Vec<(Token, Vec<Token>)> = vec![];
+        while !self.check(RightBrace) && !self.is_at_end() {
+            let variant = self.consume(Identifier, "Expected variant name")?;
+            if variants
+                .iter()
+                .any(|(other, _)| other.lexeme == variant.lexeme)
             {
-            $( result |= $parser.match_token($token); )*
+                return Err(format!(
+                    "Duplicate variant '{}' in enum {} at line {}",
+                    variant.lexeme, name.lexeme, variant.line_number
+                ));
+            }
+
+            let mut fields: Vec<Token> = vec![];
+            if self.match_token(LeftParen) {
+                while !self.check(RightParen) && !self.is_at_end() {
+                    let field = self.consume(Identifier, "Expected field name")?;
+                    if fields.iter().any(|other| other.lexeme == field.lexeme) {
+                        return Err(format!(
+                            "Duplicate field '{}' in variant {} at line {}",
+                            field.lexeme, variant.lexeme, field.line_number
+                        ));
+                    }
+                    fields.push(field);
+
+                    if !self.match_token(Comma) {
+                        break;
+                    }
+                }
+                self.consume(RightParen, "Expected ')' after variant fields")?;
+            }
+            variants.push((variant, fields));
+
+            if !self.match_token(Comma) {
+                break;
+            }
+        }
+        self.consume(RightBrace, "Expected '}' after enum variants")?;
+
+        Ok(Stmt::Enum { name, variants })
+    }
+
+    fn function(self: &mut Self, kind: &str) -> Result<Stmt, String> {
+        let name = self.consume(Identifier, &format!("Expected {} name", kind))?;
+        if kind == "function" {
+            self.declare(&name, false);
+        }
+        self.consume(LeftParen, &format!("Expected '(' after {} name", kind))?;
+        let params = self.parameters()?;
+        self.consume(LeftBrace, &format!("Expected '{{' before {} body", kind))?;
+        let (body, is_generator) = self.function_body(&params, |parser| parser.block())?;
+
+        Ok(Stmt::Function {
+            name,
+            params: Rc::new(params),
+            body: Rc::new(body),
+            is_generator,
+        })
+    }
+
+    /// Parses a parameter list after its opening '(', up to and including the
+    /// ')'. Parameters with defaults come after the required ones, and a
+    /// variadic `...rest` parameter comes last.
+    fn parameters(self: &mut Self) -> Result<Vec<Param>, String> {
+        let mut params: Vec<Param> = vec![];
+        if !self.check(RightParen) {
+            loop {
+                let variadic = self.match_token(DotDotDot);
+                let name = self.consume(Identifier, "Expected parameter name")?;
+
+                if params.iter().any(|param| param.name.lexeme == name.lexeme) {
+                    return Err(format!(
+                        "Duplicate parameter '{}' at line {}",
+                        name.lexeme, name.line_number
+                    ));
+                }
+                if let Some(last) = params.last().filter(|param| param.variadic) {
+                    return Err(format!(
+                        "Variadic parameter '{}' must be the last parameter at line {}",
+                        last.name.lexeme, name.line_number
+                    ));
+                }
+
+                let default = if !variadic && self.match_token(Equal) {
+                    Some(self.expression()?)
+                } else {
+                    None
+                };
+                if default.is_none()
+                    && !variadic
+                    && params.iter().any(|param| param.default.is_some())
+                {
+                    return Err(format!(
+                        "Parameter '{}' without a default can't follow one with a default at line {}",
+                        name.lexeme, name.line_number
+                    ));
+                }
+
+                params.push(Param {
+                    name,
+                    default,
+                    variadic,
+                });
+
+                if !self.match_token(Comma) {
+                    break;
+                }
             }
-            result
         }
+
+        self.consume(RightParen, "Expected ')' after parameters")?;
+        Ok(params)
     }
-}
 
-impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+    /// Parses a function body with `parse`, reporting whether it contains a
+    /// `yield` and so makes the function a generator. Nested functions track
+    /// their own yields, and can't break out of loops around them.
+    fn function_body(
+        self: &mut Self,
+        params: &[Param],
+        parse: impl FnOnce(&mut Self) -> Result<Vec<Stmt>, String>,
+    ) -> Result<(Vec<Stmt>, bool), String> {
+        self.function_depth += 1;
+        let enclosing_yield = std::mem::replace(&mut self.yield_found, false);
+        let enclosing_loops = std::mem::take(&mut self.loops);
+        let enclosing_switches = std::mem::replace(&mut self.switch_depth, 0);
+        let body = self.scoped(|parser| {
+            for param in params {
+                parser.declare(&param.name, false);
+            }
+            parse(parser)
+        });
+        let is_generator = std::mem::replace(&mut self.yield_found, enclosing_yield);
+        self.loops = enclosing_loops;
+        self.switch_depth = enclosing_switches;
+        self.function_depth -= 1;
+
+        Ok((body?, is_generator))
+    }
+
+    /// Parses `fun (a, b) { ... }` after the `fun` keyword.
+    fn lambda(self: &mut Self) -> Result<Expr, String> {
+        let keyword = self.previous();
+        self.consume(LeftParen, "Expected '(' after 'fun'")?;
+        let params = self.parameters()?;
+        self.consume(LeftBrace, "Expected '{' before lambda body")?;
+        let (body, is_generator) = self.function_body(&params, |parser| parser.block())?;
+
+        Ok(Lambda {
+            keyword,
+            params: Rc::new(params),
+            body: Rc::new(body),
+            is_generator,
+        })
+    }
+
+    /// Parses the body after the '=>' of an arrow function: either a block
+    /// or a single expression whose value is returned.
+    fn arrow_function(self: &mut Self, params: Vec<Param>) -> Result<Expr, String> {
+        let arrow = self.previous();
+        let (body, is_generator) = self.function_body(&params, |parser| {
+            if parser.check(LeftBrace) && !parser.starts_map_literal() {
+                parser.advance();
+                parser.block()
+            } else {
+                Ok(vec![Stmt::Return {
+                    keyword: arrow.clone(),
+                    value: parser.expression()?,
+                }])
+            }
+        })?;
+
+        Ok(Lambda {
+            keyword: arrow,
+            params: Rc::new(params),
+            body: Rc::new(body),
+            is_generator,
+        })
+    }
+
+    /// Whether the '(' at the current token opens the parameters of an arrow
+    /// function, as in `(a, b) => a + b`, rather than a grouping or tuple.
+    fn starts_arrow_function(self: &Self) -> bool {
+        !self.no_arrow && self.token_after_parens() == Some(EqualGreater)
+    }
+
+    /// The type of the token after the parenthesized group that starts at
+    /// the current '('.
+    fn token_after_parens(self: &Self) -> Option<TokenType> {
+        let mut depth = 0;
+        for (i, token) in self.tokens.iter().enumerate().skip(self.current) {
+            match token.token_type {
+                LeftParen => depth += 1,
+                RightParen => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return self.tokens.get(i + 1).map(|token| token.token_type);
+                    }
+                }
+                Eof => return None,
+                _ => (),
+            }
+        }
+
+        None
+    }
+
+    fn var_declaration(self: &mut Self) -> Result<Stmt, String> {
+        if self.match_token(LeftParen) {
+            return self.destructuring_declaration();
+        }
+
+        let name = self.consume(Identifier, "Expected variable name")?;
+
+        let initializer = if self.match_token(Equal) {
+            self.expression()?
+        } else {
+            Literal {
+                value: LiteralValue::Nil,
+            }
+        };
+
+        self.consume(Semicolon, "Expected ';' after variable declaration")?;
+        self.declare(&name, false);
+
+        Ok(Stmt::Var { name, initializer })
+    }
+
+    fn const_declaration(self: &mut Self) -> Result<Stmt, String> {
+        let name = self.consume(Identifier, "Expected constant name")?;
+        self.consume(Equal, "Expected '=' after constant name")?;
+        let initializer = self.expression()?;
+        self.consume(Semicolon, "Expected ';' after constant declaration")?;
+        self.declare(&name, true);
+
+        Ok(Stmt::Const { name, initializer })
+    }
+
+    fn destructuring_declaration(self: &mut Self) -> Result<Stmt, String> {
+        let paren = self.previous();
+
+        let mut names = vec![];
+        while !self.check(RightParen) && !self.is_at_end() {
+            names.push(self.consume(Identifier, "Expected variable name in destructuring")?);
+
+            if !self.match_token(Comma) {
+                break;
+            }
+        }
+
+        self.consume(RightParen, "Expected ')' after variable names")?;
+        self.consume(Equal, "Expected '=' after destructuring pattern")?;
+        let initializer = self.expression()?;
+        self.consume(Semicolon, "Expected ';' after variable declaration")?;
+        for name in &names {
+            self.declare(name, false);
+        }
+
+        Ok(Stmt::Destructure {
+            names,
+            paren,
+            initializer,
+        })
+    }
+
+    fn statement(self: &mut Self) -> Result<Stmt, String> {
+        if self.match_token(Print) {
+            self.print_statement()
+        } else if self.match_token(Return) {
+            self.return_statement()
+        } else if self.match_token(Yield) {
+            self.yield_statement()
+        } else if self.match_token(If) {
+            self.if_statement()
+        } else if self.match_token(Throw) {
+            self.throw_statement()
+        } else if self.match_token(Try) {
+            self.try_statement()
+        } else if self.match_token(Switch) {
+            self.switch_statement()
+        } else if self.match_token(Break) || self.match_token(Continue) {
+            self.loop_control_statement()
+        } else if self.match_token(While) {
+            self.while_statement(None)
+        } else if self.match_token(For) {
+            self.for_statement(None)
+        } else if self.check(Identifier) && self.check_next(Colon) {
+            let label = self.advance();
+            self.advance();
+            if self.match_token(While) {
+                self.while_statement(Some(label))
+            } else if self.match_token(For) {
+                self.for_statement(Some(label))
+            } else {
+                Err(format!(
+                    "Expected a loop after label '{}' at line {}",
+                    label.lexeme, label.line_number
+                ))
+            }
+        } else if self.check(LeftBrace) && !self.starts_map_literal() {
+            self.advance();
+            Ok(Stmt::Block {
+                statements: self.block()?,
+            })
+        } else {
+            self.expression_statement()
+        }
+    }
+
+    /// A `{` at the start of a statement opens a block, unless it is followed
+    /// by a literal key and a ':' as in `{"a": 1}.len();`.
+    fn starts_map_literal(self: &mut Self) -> bool {
+        if self.current + 2 >= self.tokens.len() {
+            return false;
+        }
+
+        matches!(
+            self.tokens[self.current + 1].token_type,
+            StringKing | Number | True | False | Nil
+        ) && self.tokens[self.current + 2].token_type == Colon
+    }
+
+    fn print_statement(self: &mut Self) -> Result<Stmt, String> {
+        let keyword = self.previous();
+        let value = self.expression()?;
+        self.consume(Semicolon, "Expected ';' after value")?;
+
+        Ok(Stmt::Print {
+            keyword,
+            expression: value,
+        })
+    }
+
+    fn if_statement(self: &mut Self) -> Result<Stmt, String> {
+        self.consume(LeftParen, "Expected '(' after 'if'")?;
+        let condition = self.expression()?;
+        self.consume(RightParen, "Expected ')' after if condition")?;
+
+        let then_branch = Box::from(self.statement()?);
+        let else_branch = if self.match_token(Else) {
+            Some(Box::from(self.statement()?))
+        } else {
+            None
+        };
+
+        Ok(Stmt::If {
+            condition,
+            then_branch,
+            else_branch,
+        })
+    }
+
+    fn while_statement(self: &mut Self, label: Option<Token>) -> Result<Stmt, String> {
+        self.consume(LeftParen, "Expected '(' after 'while'")?;
+        let condition = self.expression()?;
+        self.consume(RightParen, "Expected ')' after while condition")?;
+        let body = Box::from(self.loop_body(&label)?);
+
+        Ok(Stmt::While {
+            label,
+            condition,
+            body,
+            increment: None,
+        })
+    }
+
+    fn for_statement(self: &mut Self, label: Option<Token>) -> Result<Stmt, String> {
+        let keyword = self.previous();
+        self.consume(LeftParen, "Expected '(' after 'for'")?;
+
+        let for_in = match self.peek().token_type {
+            Identifier => self.check_next(In),
+            LeftParen => self.token_after_parens() == Some(In),
+            _ => false,
+        };
+        if !for_in {
+            return self.scoped(|parser| parser.c_style_for(label));
+        }
+
+        let (names, paren) = self.loop_variables()?;
+        self.consume(In, "Expected 'in' after loop variable")?;
+        let iterable = self.expression()?;
+        self.consume(RightParen, "Expected ')' after for clauses")?;
+        let body = Box::from(self.scoped(|parser| {
+            for name in &names {
+                parser.declare(name, false);
+            }
+            parser.loop_body(&label)
+        })?);
+
+        Ok(Stmt::ForIn {
+            label,
+            keyword,
+            names,
+            paren,
+            iterable,
+            body,
+        })
+    }
+
+    /// Parses the rest of `for (init; condition; increment) body` into a
+    /// `while` loop, inside a block when there is an initializer.
+    fn c_style_for(self: &mut Self, label: Option<Token>) -> Result<Stmt, String> {
+        let initializer = if self.match_token(Semicolon) {
+            None
+        } else if self.match_token(Var) {
+            Some(self.var_declaration()?)
+        } else {
+            Some(self.expression_statement()?)
+        };
+
+        let condition = if self.check(Semicolon) {
+            Literal {
+                value: LiteralValue::True,
+            }
+        } else {
+            self.expression()?
+        };
+        self.consume(Semicolon, "Expected ';' after loop condition")?;
+
+        let increment = if self.check(RightParen) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(RightParen, "Expected ')' after for clauses")?;
+        let body = Box::from(self.loop_body(&label)?);
+
+        let loop_stmt = Stmt::While {
+            label,
+            condition,
+            body,
+            increment,
+        };
+        Ok(match initializer {
+            Some(initializer) => Stmt::Block {
+                statements: vec![initializer, loop_stmt],
+            },
+            None => loop_stmt,
+        })
+    }
+
+    /// Parses a loop body, within which `break` and `continue` may target the loop.
+    fn loop_body(self: &mut Self, label: &Option<Token>) -> Result<Stmt, String> {
+        self.loops.push(label.clone());
+        let body = self.statement();
+        self.loops.pop();
+
+        body
+    }
+
+    fn loop_control_statement(self: &mut Self) -> Result<Stmt, String> {
+        let keyword = self.previous();
+        let in_switch = keyword.token_type == Break && self.switch_depth > 0;
+        if self.loops.is_empty() && !in_switch {
+            self.report(format!(
+                "Can't use '{}' outside of a loop at line {}",
+                keyword.lexeme, keyword.line_number
+            ));
+        }
+
+        let label = if self.check(Identifier) {
+            let label = self.advance();
+            let known = self.loops.iter().any(|other| match other {
+                Some(other) => other.lexeme == label.lexeme,
+                None => false,
+            });
+            if !known {
+                self.report(format!(
+                    "Undefined loop label '{}' at line {}",
+                    label.lexeme, label.line_number
+                ));
+            }
+            Some(label)
+        } else {
+            None
+        };
+        self.consume(
+            Semicolon,
+            &format!("Expected ';' after '{}'", keyword.lexeme),
+        )?;
+
+        Ok(if keyword.token_type == Break {
+            Stmt::Break { keyword, label }
+        } else {
+            Stmt::Continue { keyword, label }
+        })
+    }
+
+    fn switch_statement(self: &mut Self) -> Result<Stmt, String> {
+        let keyword = self.previous();
+        self.consume(LeftParen, "Expected '(' after 'switch'")?;
+        let subject = self.expression()?;
+        self.consume(RightParen, "Expected ')' after switch subject")?;
+        self.consume(LeftBrace, "Expected '{' before switch body")?;
+
+        self.switch_depth += 1;
+        let cases = self.scoped(|parser| parser.switch_cases());
+        self.switch_depth -= 1;
+        let (cases, body) = cases?;
+        self.consume(RightBrace, "Expected '}' after switch body")?;
+
+        Ok(Stmt::Switch {
+            keyword,
+            subject,
+            cases,
+            body,
+        })
+    }
+
+    /// Parses the case labels of a switch and the statements under them,
+    /// rejecting a constant that appears in two cases or a second `default`.
+    fn switch_cases(self: &mut Self) -> Result<(Vec<SwitchCase>, Vec<Stmt>), String> {
+        let mut cases: Vec<SwitchCase> = vec![];
+        let mut seen: Vec<LiteralValue> = vec![];
+        let mut body = vec![];
+
+        while !self.check(RightBrace) && !self.is_at_end() {
+            let keyword = self.advance();
+            let mut values = vec![];
+            match keyword.token_type {
+                Case => loop {
+                    let value = self.expression()?;
+                    if let Some(constant) = value.constant() {
+                        if seen.iter().any(|other| other.equals(&constant)) {
+                            self.report(format!(
+                                "Duplicate case {} in switch at line {}",
+                                constant.to_repr(),
+                                keyword.line_number
+                            ));
+                        }
+                        seen.push(constant);
+                    }
+                    values.push(value);
+
+                    if !self.match_token(Comma) {
+                        break;
+                    }
+                },
+                Default => {
+                    if cases.iter().any(|case| case.values.is_empty()) {
+                        self.report(format!(
+                            "Duplicate default in switch at line {}",
+                            keyword.line_number
+                        ));
+                    }
+                }
+                _ => {
+                    return Err(format!(
+                        "Expected 'case' or 'default' at line {}",
+                        keyword.line_number
+                    ))
+                }
+            }
+            self.consume(Colon, &format!("Expected ':' after '{}'", keyword.lexeme))?;
+
+            cases.push(SwitchCase {
+                keyword,
+                values,
+                start: body.len(),
+            });
+            while !self.check(Case)
+                && !self.check(Default)
+                && !self.check(RightBrace)
+                && !self.is_at_end()
+            {
+                body.push(self.declaration()?);
+            }
+        }
+
+        Ok((cases, body))
     }
 
-    pub fn parse(self: &mut Self) -> Result<Expr, String> {
-        self.expression()
+    /// Parses `x` or `(a, b)` after `for`, returning the destructuring
+    /// parenthesis if there is one.
+    fn loop_variables(self: &mut Self) -> Result<(Vec<Token>, Option<Token>), String> {
+        if !self.match_token(LeftParen) {
+            let name = self.consume(Identifier, "Expected loop variable name")?;
+            return Ok((vec![name], None));
+        }
+
+        let paren = self.previous();
+        let mut names = vec![];
+        while !self.check(RightParen) && !self.is_at_end() {
+            names.push(self.consume(Identifier, "Expected loop variable name")?);
+
+            if !self.match_token(Comma) {
+                break;
+            }
+        }
+        self.consume(RightParen, "Expected ')' after loop variable names")?;
+
+        Ok((names, Some(paren)))
+    }
+
+    /// Parses the `for x in iterable if condition` clause after the element
+    /// of a comprehension, up to the closing bracket or brace.
+    fn comprehension(
+        self: &mut Self,
+        element: Expr,
+        value: Option<Expr>,
+        closing: TokenType,
+    ) -> Result<Expr, String> {
+        let keyword = self.previous();
+        let (names, paren) = self.loop_variables()?;
+        self.consume(In, "Expected 'in' after loop variable")?;
+        let iterable = self.expression()?;
+
+        let condition = self.scoped(|parser| {
+            for name in &names {
+                parser.declare(name, false);
+            }
+            if parser.match_token(If) {
+                Ok(Some(Box::from(parser.expression()?)))
+            } else {
+                Ok(None)
+            }
+        })?;
+        self.consume(closing, "Expected end of comprehension")?;
+
+        Ok(Comprehension {
+            keyword,
+            element: Box::from(element),
+            value: value.map(Box::from),
+            names,
+            paren,
+            iterable: Box::from(iterable),
+            condition,
+        })
+    }
+
+    /// Parses the subject and arms of `match value { pattern => body, ... }`
+    /// after the `match` keyword.
+    fn match_expression(self: &mut Self) -> Result<Expr, String> {
+        let keyword = self.previous();
+        let subject = self.expression()?;
+        self.consume(LeftBrace, "Expected '{' after match subject")?;
+
+        let mut arms = vec![];
+        while !self.check(RightBrace) && !self.is_at_end() {
+            let mut names = vec![];
+            let pattern = self.pattern(&mut names)?;
+            let (guard, body) = self.scoped(|parser| {
+                for name in &names {
+                    parser.declare(name, false);
+                }
+                let guard = if parser.match_token(If) {
+                    let no_arrow = std::mem::replace(&mut parser.no_arrow, true);
+                    let guard = parser.expression();
+                    parser.no_arrow = no_arrow;
+                    Some(guard?)
+                } else {
+                    None
+                };
+                parser.consume(EqualGreater, "Expected '=>' after pattern")?;
+                Ok((guard, parser.expression()?))
+            })?;
+            arms.push(MatchArm {
+                pattern,
+                guard,
+                body,
+            });
+
+            if !self.match_token(Comma) {
+                break;
+            }
+        }
+
+        self.consume(RightBrace, "Expected '}' after match arms")?;
+        if arms.is_empty() {
+            return Err(format!(
+                "Expected at least one arm in match at line {}",
+                keyword.line_number
+            ));
+        }
+
+        Ok(Expr::Match {
+            keyword,
+            subject: Box::from(subject),
+            arms: Rc::new(arms),
+        })
+    }
+
+    /// Parses a single pattern, recording the names it binds so that a name
+    /// bound twice is rejected.
+    fn pattern(self: &mut Self, names: &mut Vec<Token>) -> Result<Pattern, String> {
+        let token = self.advance();
+        match token.token_type {
+            False | True | Nil | Number | StringKing => {
+                Ok(Pattern::Literal(LiteralValue::from_token(token)))
+            }
+            Minus => {
+                let number = self.consume(Number, "Expected number after '-' in pattern")?;
+                match LiteralValue::from_token(number) {
+                    LiteralValue::Number(value) => {
+                        Ok(Pattern::Literal(LiteralValue::Number(-value)))
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            Identifier if token.lexeme == "_" => Ok(Pattern::Wildcard),
+            Identifier if self.check(Dot) || self.check(LeftParen) => {
+                let mut constructor = Variable { name: token };
+                while self.match_token(Dot) {
+                    constructor = Get {
+                        object: Box::from(constructor),
+                        name: self.consume(Identifier, "Expected variant name after '.'")?,
+                        optional: false,
+                    };
+                }
+
+                let fields = if self.match_token(LeftParen) {
+                    let mut fields = vec![];
+                    while !self.check(RightParen) && !self.is_at_end() {
+                        fields.push(self.pattern(names)?);
+
+                        if !self.match_token(Comma) {
+                            break;
+                        }
+                    }
+                    self.consume(RightParen, "Expected ')' after variant fields")?;
+                    Some(fields)
+                } else {
+                    None
+                };
+
+                Ok(Pattern::Variant {
+                    constructor,
+                    fields,
+                })
+            }
+            Identifier => Ok(Pattern::Binding(Self::bind_name(token, names)?)),
+            LeftBracket => {
+                let mut elements = vec![];
+                let mut rest = None;
+                while !self.check(RightBracket) && !self.is_at_end() {
+                    if self.match_token(DotDotDot) {
+                        let name = self.consume(Identifier, "Expected name after '...'")?;
+                        rest = Some(Self::bind_name(name, names)?);
+                        break;
+                    }
+                    elements.push(self.pattern(names)?);
+
+                    if !self.match_token(Comma) {
+                        break;
+                    }
+                }
+
+                self.consume(RightBracket, "Expected ']' after list pattern")?;
+                Ok(Pattern::List { elements, rest })
+            }
+            LeftParen => {
+                let mut elements = vec![];
+                let mut is_tuple = false;
+                while !self.check(RightParen) && !self.is_at_end() {
+                    elements.push(self.pattern(names)?);
+
+                    if !self.match_token(Comma) {
+                        break;
+                    }
+                    is_tuple = true;
+                }
+
+                self.consume(RightParen, "Expected ')' after pattern")?;
+                if elements.len() == 1 && !is_tuple {
+                    Ok(elements.pop().unwrap())
+                } else {
+                    Ok(Pattern::Tuple(elements))
+                }
+            }
+            LeftBrace => {
+                let mut entries = vec![];
+                while !self.check(RightBrace) && !self.is_at_end() {
+                    let key = self.advance();
+                    let pattern = match key.token_type {
+                        Identifier if !self.check(Colon) => {
+                            Pattern::Binding(Self::bind_name(key.clone(), names)?)
+                        }
+                        Identifier | StringKing | Number => {
+                            self.consume(Colon, "Expected ':' after key in map pattern")?;
+                            self.pattern(names)?
+                        }
+                        _ => {
+                            return Err(format!(
+                                "Expected key in map pattern at line {}",
+                                key.line_number
+                            ))
+                        }
+                    };
+                    let key = match key.token_type {
+                        Identifier => LiteralValue::StringValue(key.lexeme),
+                        _ => LiteralValue::from_token(key),
+                    };
+                    entries.push((key, pattern));
+
+                    if !self.match_token(Comma) {
+                        break;
+                    }
+                }
+
+                self.consume(RightBrace, "Expected '}' after map pattern")?;
+                Ok(Pattern::Map(entries))
+            }
+            _ => Err(format!("Expected pattern at line {}", token.line_number)),
+        }
+    }
+
+    fn bind_name(name: Token, names: &mut Vec<Token>) -> Result<Token, String> {
+        if names.iter().any(|other| other.lexeme == name.lexeme) {
+            return Err(format!(
+                "Duplicate binding '{}' in pattern at line {}",
+                name.lexeme, name.line_number
+            ));
+        }
+        names.push(name.clone());
+
+        Ok(name)
+    }
+
+    fn return_statement(self: &mut Self) -> Result<Stmt, String> {
+        let keyword = self.previous();
+        if self.function_depth == 0 {
+            self.report(format!(
+                "Can't return from top-level code at line {}",
+                keyword.line_number
+            ));
+        }
+
+        let value = if self.check(Semicolon) {
+            Literal {
+                value: LiteralValue::Nil,
+            }
+        } else {
+            self.expression()?
+        };
+        self.consume(Semicolon, "Expected ';' after return value")?;
+
+        Ok(Stmt::Return { keyword, value })
+    }
+
+    fn yield_statement(self: &mut Self) -> Result<Stmt, String> {
+        let keyword = self.previous();
+        if self.function_depth == 0 {
+            self.report(format!(
+                "Can't yield from top-level code at line {}",
+                keyword.line_number
+            ));
+        }
+        self.yield_found = true;
+
+        let value = if self.check(Semicolon) {
+            Literal {
+                value: LiteralValue::Nil,
+            }
+        } else {
+            self.expression()?
+        };
+        self.consume(Semicolon, "Expected ';' after yield value")?;
+
+        Ok(Stmt::Yield { keyword, value })
+    }
+
+    fn throw_statement(self: &mut Self) -> Result<Stmt, String> {
+        let keyword = self.previous();
+        let value = self.expression()?;
+        self.consume(Semicolon, "Expected ';' after thrown value")?;
+
+        Ok(Stmt::Throw { keyword, value })
+    }
+
+    fn try_statement(self: &mut Self) -> Result<Stmt, String> {
+        let keyword = self.previous();
+        self.consume(LeftBrace, "Expected '{' after 'try'")?;
+        let body = self.block()?;
+
+        let catch = if self.match_token(Catch) {
+            self.consume(LeftParen, "Expected '(' after 'catch'")?;
+            let name = self.consume(Identifier, "Expected error variable name")?;
+            self.consume(RightParen, "Expected ')' after error variable")?;
+            self.consume(LeftBrace, "Expected '{' before catch body")?;
+            let body = self.scoped(|parser| {
+                parser.declare(&name, false);
+                parser.block()
+            })?;
+            Some((name, body))
+        } else {
+            None
+        };
+
+        let finally = if self.match_token(Finally) {
+            self.consume(LeftBrace, "Expected '{' after 'finally'")?;
+            Some(self.block()?)
+        } else {
+            None
+        };
+
+        if catch.is_none() && finally.is_none() {
+            return Err(format!(
+                "Expected 'catch' or 'finally' after try block at line {}",
+                keyword.line_number
+            ));
+        }
+
+        Ok(Stmt::Try {
+            body,
+            catch,
+            finally,
+        })
+    }
+
+    fn block(self: &mut Self) -> Result<Vec<Stmt>, String> {
+        self.scoped(|parser| {
+            let mut statements = vec![];
+
+            while !parser.check(RightBrace) && !parser.is_at_end() {
+                statements.push(parser.declaration()?);
+            }
+
+            parser.consume(RightBrace, "Expected '}' after block")?;
+
+            Ok(statements)
+        })
+    }
+
+    /// Records an error in code that is otherwise well-formed. Parsing
+    /// carries on, rather than skipping ahead to the next statement.
+    fn report(self: &mut Self, message: String) {
+        self.errors.push(message);
+    }
+
+    /// Runs `parse` in a new scope for declarations.
+    fn scoped<T>(
+        self: &mut Self,
+        parse: impl FnOnce(&mut Self) -> Result<T, String>,
+    ) -> Result<T, String> {
+        self.scopes.push(HashMap::new());
+        let result = parse(self);
+        self.scopes.pop();
+
+        result
+    }
+
+    /// Declares `name` in the innermost scope, reporting it if it is already
+    /// a constant there. Constants are enforced when the program runs; this
+    /// catches the mistakes visible in the source early.
+    fn declare(self: &mut Self, name: &Token, constant: bool) {
+        let scope = self.scopes.last_mut().unwrap();
+        if let Some(Some(declaration)) = scope.get(&name.lexeme) {
+            let message = format!(
+                "Can't redeclare constant '{}' at line {} (declared at line {})",
+                name.lexeme, name.line_number, declaration.line_number
+            );
+            self.report(message);
+            return;
+        }
+        scope.insert(name.lexeme.clone(), constant.then(|| name.clone()));
+    }
+
+    /// Reports an assignment to `target` if it is a variable that resolves to
+    /// a constant declared earlier in the source.
+    fn check_assignable(self: &mut Self, target: &Expr) {
+        let name = match target {
+            Variable { name } => name,
+            _ => return,
+        };
+
+        for scope in self.scopes.iter().rev() {
+            match scope.get(&name.lexeme) {
+                Some(Some(declaration)) => {
+                    let message = format!(
+                        "Can't assign to constant '{}' at line {} (declared at line {})",
+                        name.lexeme, name.line_number, declaration.line_number
+                    );
+                    self.report(message);
+                    return;
+                }
+                Some(None) => return,
+                None => (),
+            }
+        }
+    }
+
+    fn expression_statement(self: &mut Self) -> Result<Stmt, String> {
+        let expr = self.expression()?;
+        self.consume(Semicolon, "Expected ';' after expression")?;
+
+        Ok(Stmt::Expression { expression: expr })
     }
 
     fn expression(self: &mut Self) -> Result<Expr, String> {
-        self.equality()
+        self.assignment()
+    }
+
+    fn assignment(self: &mut Self) -> Result<Expr, String> {
+        let expr = self.pipeline()?;
+
+        if self.match_token(Equal) {
+            let equals = self.previous();
+            let value = self.assignment()?;
+            match &expr {
+                Tuple { elements, .. } => {
+                    for element in elements {
+                        self.check_assignable(element);
+                    }
+                }
+                target => self.check_assignable(target),
+            }
+
+            match expr {
+                Variable { name } => Ok(Assign {
+                    name,
+                    value: Box::from(value),
+                }),
+                Index {
+                    object,
+                    bracket,
+                    index,
+                } => Ok(SetIndex {
+                    object,
+                    bracket,
+                    index,
+                    value: Box::from(value),
+                }),
+                Get {
+                    object,
+                    name,
+                    optional: false,
+                } => Ok(Set {
+                    object,
+                    name,
+                    value: Box::from(value),
+                }),
+                Tuple { paren, elements }
+                    if elements
+                        .iter()
+                        .all(|element| element.is_assignment_target()) =>
+                {
+                    Ok(Destructure {
+                        targets: elements,
+                        paren,
+                        value: Box::from(value),
+                    })
+                }
+                _ => Err(format!(
+                    "Invalid assignment target at line {}",
+                    equals.line_number
+                )),
+            }
+        } else if self.match_tokens(&[PlusEqual, MinusEqual, StarEqual, SlashEqual, PercentEqual]) {
+            let operator = self.previous();
+            let value = self.assignment()?;
+
+            if !expr.is_assignment_target() {
+                return Err(format!(
+                    "Invalid assignment target for '{}' at line {}",
+                    operator.lexeme, operator.line_number
+                ));
+            }
+            self.check_assignable(&expr);
+
+            Ok(CompoundAssign {
+                target: Box::from(expr),
+                operator,
+                value: Box::from(value),
+            })
+        } else {
+            Ok(expr)
+        }
+    }
+
+    fn pipeline(self: &mut Self) -> Result<Expr, String> {
+        let mut expr = self.conditional()?;
+
+        while self.match_token(PipeGreater) {
+            let operator = self.previous();
+            let rhs = self.conditional()?;
+            expr = Pipeline {
+                left: Box::from(expr),
+                operator,
+                right: Box::from(rhs),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn conditional(self: &mut Self) -> Result<Expr, String> {
+        let condition = self.nil_coalesce()?;
+
+        if self.match_token(Question) {
+            let then_branch = self.expression()?;
+            self.consume(Colon, "Expected ':' in conditional expression")?;
+            let else_branch = self.conditional()?;
+
+            Ok(Conditional {
+                condition: Box::from(condition),
+                then_branch: Box::from(then_branch),
+                else_branch: Box::from(else_branch),
+            })
+        } else {
+            Ok(condition)
+        }
+    }
+
+    fn nil_coalesce(self: &mut Self) -> Result<Expr, String> {
+        let mut expr = self.equality()?;
+
+        while self.match_token(QuestionQuestion) {
+            let operator = self.previous();
+            let rhs = self.equality()?;
+            expr = Logical {
+                left: Box::from(expr),
+                operator,
+                right: Box::from(rhs),
+            };
+        }
+
+        Ok(expr)
     }
 
     fn equality(self: &mut Self) -> Result<Expr, String> {
@@ -39,7 +1460,7 @@ impl Parser {
             let rhs = self.comparison()?;
             expr = Binary {
                 left: Box::from(expr),
-                operator: operator,
+                operator,
                 right: Box::from(rhs),
             };
         }
@@ -48,16 +1469,106 @@ impl Parser {
     }
 
     fn comparison(self: &mut Self) -> Result<Expr, String> {
+        let mut expr = self.range()?;
+
+        while self.match_tokens(&[Greater, GreaterEqual, Less, LessEqual, Is]) {
+            let operator = self.previous();
+            let rhs = self.range()?;
+            expr = Binary {
+                left: Box::from(expr),
+                operator,
+                right: Box::from(rhs),
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn range(self: &mut Self) -> Result<Expr, String> {
+        let start = self.bitwise_or()?;
+
+        if !self.match_tokens(&[DotDot, DotDotEqual]) {
+            return Ok(start);
+        }
+
+        let operator = self.previous();
+        let end = self.bitwise_or()?;
+
+        // `step` is only a keyword right after a range.
+        let step = if self.check(Identifier) && self.peek().lexeme == "step" {
+            self.advance();
+            Some(Box::from(self.bitwise_or()?))
+        } else {
+            None
+        };
+
+        Ok(Range {
+            start: Box::from(start),
+            operator,
+            end: Box::from(end),
+            step,
+        })
+    }
+
+    fn bitwise_or(self: &mut Self) -> Result<Expr, String> {
+        let mut expr = self.bitwise_xor()?;
+
+        while self.match_tokens(&[Pipe]) {
+            let operator = self.previous();
+            let rhs = self.bitwise_xor()?;
+            expr = Binary {
+                left: Box::from(expr),
+                operator,
+                right: Box::from(rhs),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn bitwise_xor(self: &mut Self) -> Result<Expr, String> {
+        let mut expr = self.bitwise_and()?;
+
+        while self.match_tokens(&[Caret]) {
+            let operator = self.previous();
+            let rhs = self.bitwise_and()?;
+            expr = Binary {
+                left: Box::from(expr),
+                operator,
+                right: Box::from(rhs),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn bitwise_and(self: &mut Self) -> Result<Expr, String> {
+        let mut expr = self.shift()?;
+
+        while self.match_tokens(&[Ampersand]) {
+            let operator = self.previous();
+            let rhs = self.shift()?;
+            expr = Binary {
+                left: Box::from(expr),
+                operator,
+                right: Box::from(rhs),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn shift(self: &mut Self) -> Result<Expr, String> {
         let mut expr = self.term()?;
 
-        while self.match_tokens(&[Greater, GreaterEqual, Less, LessEqual]) {
+        while self.match_tokens(&[LessLess, GreaterGreater]) {
             let operator = self.previous();
             let rhs = self.term()?;
             expr = Binary {
                 left: Box::from(expr),
-                operator: operator,
+                operator,
                 right: Box::from(rhs),
-            }
+            };
         }
 
         Ok(expr)
@@ -71,7 +1582,7 @@ impl Parser {
             let rhs = self.factor()?;
             expr = Binary {
                 left: Box::from(expr),
-                operator: operator,
+                operator,
                 right: Box::from(rhs),
             };
         }
@@ -82,12 +1593,12 @@ impl Parser {
     fn factor(self: &mut Self) -> Result<Expr, String> {
         let mut expr = self.unary()?;
 
-        while self.match_tokens(&[Slash, Star]) {
+        while self.match_tokens(&[Slash, Star, Percent]) {
             let operator = self.previous();
             let rhs = self.unary()?;
             expr = Binary {
                 left: Box::from(expr),
-                operator: operator,
+                operator,
                 right: Box::from(rhs),
             };
         }
@@ -96,17 +1607,155 @@ impl Parser {
     }
 
     fn unary(self: &mut Self) -> Result<Expr, String> {
-        if self.match_tokens(&[Bang, Minus]) {
+        if self.match_tokens(&[Bang, Minus, Tilde]) {
             let operator = self.previous();
             let rhs = self.unary()?;
 
-            Ok(Unary {
-                operator: operator,
-                right: Box::from(rhs),
-            })
-        } else {
-            self.primary()
+            Ok(Unary {
+                operator,
+                right: Box::from(rhs),
+            })
+        } else if self.match_tokens(&[PlusPlus, MinusMinus]) {
+            let operator = self.previous();
+            let target = self.unary()?;
+
+            self.increment(target, operator, true)
+        } else {
+            self.postfix()
+        }
+    }
+
+    fn postfix(self: &mut Self) -> Result<Expr, String> {
+        let expr = self.call()?;
+
+        if self.match_tokens(&[PlusPlus, MinusMinus]) {
+            let operator = self.previous();
+            self.increment(expr, operator, false)
+        } else {
+            Ok(expr)
+        }
+    }
+
+    fn call(self: &mut Self) -> Result<Expr, String> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.match_token(LeftParen) {
+                expr = self.finish_call(expr)?;
+            } else if self.match_token(LeftBracket) {
+                expr = self.finish_index(expr)?;
+            } else if self.match_tokens(&[Dot, QuestionDot]) {
+                let optional = self.previous().token_type == QuestionDot;
+                let name = self.consume(Identifier, "Expected property name after '.'")?;
+                expr = Get {
+                    object: Box::from(expr),
+                    name,
+                    optional,
+                };
+            } else {
+                break;
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn finish_index(self: &mut Self, object: Expr) -> Result<Expr, String> {
+        let bracket = self.previous();
+
+        let start = if self.check(Colon) {
+            None
+        } else {
+            Some(Box::from(self.expression()?))
+        };
+
+        if !self.match_token(Colon) {
+            self.consume(RightBracket, "Expected ']' after index")?;
+            return match start {
+                Some(index) => Ok(Index {
+                    object: Box::from(object),
+                    bracket,
+                    index,
+                }),
+                None => Err(format!("Expected index at line {}", bracket.line_number)),
+            };
+        }
+
+        let end = if self.check(RightBracket) {
+            None
+        } else {
+            Some(Box::from(self.expression()?))
+        };
+        self.consume(RightBracket, "Expected ']' after slice")?;
+
+        Ok(Slice {
+            object: Box::from(object),
+            bracket,
+            start,
+            end,
+        })
+    }
+
+    fn finish_call(self: &mut Self, callee: Expr) -> Result<Expr, String> {
+        let mut arguments = vec![];
+        let mut named: Vec<(Token, Expr)> = vec![];
+
+        if !self.check(RightParen) {
+            loop {
+                if self.check(Identifier) && self.check_next(Colon) {
+                    let name = self.advance();
+                    self.advance();
+                    if named.iter().any(|(other, _)| other.lexeme == name.lexeme) {
+                        return Err(format!(
+                            "Duplicate named argument '{}' at line {}",
+                            name.lexeme, name.line_number
+                        ));
+                    }
+                    named.push((name, self.expression()?));
+                } else if let Some((name, _)) = named.last() {
+                    return Err(format!(
+                        "Positional argument can't follow named argument '{}' at line {}",
+                        name.lexeme, name.line_number
+                    ));
+                } else {
+                    arguments.push(self.expression()?);
+                }
+
+                if !self.match_token(Comma) {
+                    break;
+                }
+            }
+        }
+
+        let paren = self.consume(RightParen, "Expected ')' after arguments")?;
+
+        Ok(Call {
+            callee: Box::from(callee),
+            paren,
+            arguments,
+            named,
+        })
+    }
+
+    fn increment(
+        self: &mut Self,
+        target: Expr,
+        operator: Token,
+        prefix: bool,
+    ) -> Result<Expr, String> {
+        if !target.is_assignment_target() {
+            return Err(format!(
+                "Invalid operand for '{}' at line {}",
+                operator.lexeme, operator.line_number
+            ));
         }
+        self.check_assignable(&target);
+
+        Ok(Increment {
+            target: Box::from(target),
+            operator,
+            prefix,
+        })
     }
 
     fn primary(self: &mut Self) -> Result<Expr, String> {
@@ -115,12 +1764,43 @@ impl Parser {
         let result;
         match token.token_type {
             LeftParen => {
+                if self.starts_arrow_function() {
+                    self.advance();
+                    let params = self.parameters()?;
+                    self.consume(EqualGreater, "Expected '=>' after parameters")?;
+                    return self.arrow_function(params);
+                }
+
                 self.advance();
+                if self.match_token(RightParen) {
+                    return Ok(Tuple {
+                        paren: token,
+                        elements: vec![],
+                    });
+                }
+
                 let expr = self.expression()?;
-                self.consume(RightParen, "Expected ')'")?;
-                result = Grouping {
-                    expression: Box::from(expr),
-                };
+                if self.match_token(Comma) {
+                    let mut elements = vec![expr];
+                    while !self.check(RightParen) && !self.is_at_end() {
+                        elements.push(self.expression()?);
+
+                        if !self.match_token(Comma) {
+                            break;
+                        }
+                    }
+
+                    self.consume(RightParen, "Expected ')' after tuple elements")?;
+                    result = Tuple {
+                        paren: token,
+                        elements,
+                    };
+                } else {
+                    self.consume(RightParen, "Expected ')'")?;
+                    result = Grouping {
+                        expression: Box::from(expr),
+                    };
+                }
             }
 
             False | True | Nil | Number | StringKing => {
@@ -130,19 +1810,108 @@ impl Parser {
                 }
             }
 
+            Identifier => {
+                self.advance();
+                if !self.no_arrow && self.match_token(EqualGreater) {
+                    let param = Param {
+                        name: token,
+                        default: None,
+                        variadic: false,
+                    };
+                    return self.arrow_function(vec![param]);
+                }
+                result = Variable { name: token };
+            }
+
+            Fun => {
+                self.advance();
+                return self.lambda();
+            }
+
+            TokenType::Match => {
+                self.advance();
+                return self.match_expression();
+            }
+
+            This => {
+                self.advance();
+                if self.class_depth == 0 {
+                    return Err(format!(
+                        "Can't use 'this' outside of a class at line {}",
+                        token.line_number
+                    ));
+                }
+                result = Expr::This { keyword: token };
+            }
+
+            LeftBrace => {
+                self.advance();
+                let mut entries = vec![];
+
+                while !self.check(RightBrace) && !self.is_at_end() {
+                    let key = self.expression()?;
+                    self.consume(Colon, "Expected ':' after map key")?;
+                    let value = self.expression()?;
+                    if entries.is_empty() && self.match_token(For) {
+                        return self.comprehension(key, Some(value), RightBrace);
+                    }
+                    entries.push((key, value));
+
+                    if !self.match_token(Comma) {
+                        break;
+                    }
+                }
+
+                self.consume(RightBrace, "Expected '}' after map entries")?;
+                result = Map {
+                    brace: token,
+                    entries,
+                };
+            }
+
+            LeftBracket => {
+                self.advance();
+                let mut elements = vec![];
+
+                while !self.check(RightBracket) && !self.is_at_end() {
+                    let element = self.expression()?;
+                    if elements.is_empty() && self.match_token(For) {
+                        return self.comprehension(element, None, RightBracket);
+                    }
+                    elements.push(element);
+
+                    if !self.match_token(Comma) {
+                        break;
+                    }
+                }
+
+                self.consume(RightBracket, "Expected ']' after list elements")?;
+                result = List { elements };
+            }
+
             _ => return Err("Expected expression".to_string()),
         }
 
         Ok(result)
     }
 
-    fn consume(self: &mut Self, token_type: TokenType, msg: &str) -> Result<(), String> {
+    fn consume(self: &mut Self, token_type: TokenType, msg: &str) -> Result<Token, String> {
         let token = self.peek();
         if token.token_type == token_type {
-            self.advance();
-            Ok(())
+            Ok(self.advance())
         } else {
-            Err(msg.to_string())
+            Err(format!("{} at line {}", msg, token.line_number))
+        }
+    }
+
+    fn check(self: &mut Self, token_type: TokenType) -> bool {
+        self.peek().token_type == token_type
+    }
+
+    fn check_next(self: &mut Self, token_type: TokenType) -> bool {
+        match self.tokens.get(self.current + 1) {
+            Some(token) => token.token_type == token_type,
+            None => false,
         }
     }
 
@@ -195,14 +1964,32 @@ impl Parser {
             if self.previous().token_type == Semicolon {
                 return;
             }
-        }
 
-        match self.peek().token_type {
-            Class | Fun | Var | For | If | While | Print | Return => return,
-            _ => (),
-        }
+            match self.peek().token_type {
+                Class
+                | Const
+                | TokenType::Enum
+                | Export
+                | Import
+                | TokenType::Trait
+                | Fun
+                | Var
+                | For
+                | If
+                | While
+                | Print
+                | Return
+                | Yield
+                | Break
+                | Continue
+                | Throw
+                | Try
+                | Switch => return,
+                _ => (),
+            }
 
-        self.advance();
+            self.advance();
+        }
     }
 }
 
@@ -240,7 +2027,7 @@ mod tests {
 
         let tokens = vec![one, plus, two, semicolon];
         let mut parser = Parser::new(tokens);
-        let parsed_expr = parser.parse();
+        let parsed_expr = parser.expression();
         let string_expr = parsed_expr.unwrap().to_string();
 
         assert_eq!(string_expr, "(+ 1 2)");
@@ -253,7 +2040,7 @@ mod tests {
         let tokens = scanner.scan_tokens();
 
         let mut parser = Parser::new(tokens.unwrap());
-        let parsed_expr = parser.parse();
+        let parsed_expr = parser.expression();
         let string_expr = parsed_expr.unwrap().to_string();
 
         assert_eq!(string_expr, "(== (+ 1 2) (+ 5 7))");
@@ -266,9 +2053,596 @@ mod tests {
         let tokens = scanner.scan_tokens();
 
         let mut parser = Parser::new(tokens.unwrap());
-        let parsed_expr = parser.parse();
+        let parsed_expr = parser.expression();
         let string_expr = parsed_expr.unwrap().to_string();
 
         assert_eq!(string_expr, "(== 2 (group (+ 2 1)))");
     }
+
+    #[test]
+    fn handle_compound_assignment_and_increments() {
+        let source = "var x = 1; x += 2 * 3; x++; --x; { x = 4; }";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens();
+
+        let mut parser = Parser::new(tokens.unwrap());
+        let stmts = parser.parse_program().unwrap();
+        let strings: Vec<String> = stmts.iter().map(|stmt| stmt.to_string()).collect();
+
+        assert_eq!(
+            strings,
+            vec![
+                "(var x 1)",
+                "(+= x (* 2 3))",
+                "(x ++)",
+                "(-- x)",
+                "(block (= x 4))"
+            ]
+        );
+    }
+
+    #[test]
+    fn handle_conditional_right_associative() {
+        let source = "1 == 2 ? 3 : 4 > 5 ? 6 : 7 + 8";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens();
+
+        let mut parser = Parser::new(tokens.unwrap());
+        let parsed_expr = parser.expression();
+        let string_expr = parsed_expr.unwrap().to_string();
+
+        assert_eq!(string_expr, "(? (== 1 2) 3 (? (> 4 5) 6 (+ 7 8)))");
+    }
+
+    #[test]
+    fn handle_nil_coalescing_and_optional_chaining() {
+        let source = "a ?? b?.c.d(1, 2) ?? e ? f : g";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens();
+
+        let mut parser = Parser::new(tokens.unwrap());
+        let parsed_expr = parser.expression();
+        let string_expr = parsed_expr.unwrap().to_string();
+
+        assert_eq!(
+            string_expr,
+            "(? (?? (?? a (call (. (?. b c) d) 1 2)) e) f g)"
+        );
+    }
+
+    #[test]
+    fn handle_pipeline_precedence() {
+        let source = "x = 1 + 2 |> f |> g(a, b) ?? h";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens();
+
+        let mut parser = Parser::new(tokens.unwrap());
+        let parsed_expr = parser.expression();
+        let string_expr = parsed_expr.unwrap().to_string();
+
+        assert_eq!(string_expr, "(= x (|> (|> (+ 1 2) f) (?? (call g a b) h)))");
+    }
+
+    #[test]
+    fn handle_list_literals_indexing_and_slicing() {
+        let source = "xs = [1, [2, 3], ][0][1:][:-1][x ? 1 : 2:]";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens();
+
+        let mut parser = Parser::new(tokens.unwrap());
+        let parsed_expr = parser.expression();
+        let string_expr = parsed_expr.unwrap().to_string();
+
+        assert_eq!(
+            string_expr,
+            "(= xs (slice (slice (slice (index (list 1 (list 2 3)) 0) 1 _) _ (- 1)) (? x 1 2) _))"
+        );
+    }
+
+    #[test]
+    fn handle_map_literals_and_blocks() {
+        let source = "var m = {\"a\": 1, k: [2]}; {\"b\": 2}.len(); { m[\"a\"] = {}; }";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens();
+
+        let mut parser = Parser::new(tokens.unwrap());
+        let stmts = parser.parse_program().unwrap();
+        let strings: Vec<String> = stmts.iter().map(|stmt| stmt.to_string()).collect();
+
+        assert_eq!(
+            strings,
+            vec![
+                "(var m (map (a 1) (k (list 2))))",
+                "(call (. (map (b 2)) len))",
+                "(block (= (index m a) (map)))"
+            ]
+        );
+    }
+
+    #[test]
+    fn handle_tuples_and_destructuring() {
+        let source = "var (a, b) = (1, (2,)); (a, b[0]) = (b, a); (); (a);";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens();
+
+        let mut parser = Parser::new(tokens.unwrap());
+        let stmts = parser.parse_program().unwrap();
+        let strings: Vec<String> = stmts.iter().map(|stmt| stmt.to_string()).collect();
+
+        assert_eq!(
+            strings,
+            vec![
+                "(var (a b) (tuple 1 (tuple 2)))",
+                "(= (tuple a (index b 0)) (tuple b a))",
+                "(tuple)",
+                "(group a)"
+            ]
+        );
+
+        let source = "(a, 1) = (1, 2);";
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        assert!(Parser::new(tokens).parse_program().is_err());
+    }
+
+    #[test]
+    fn handle_range_precedence() {
+        let source = "0..n + 1 step 2 == 1..=3 | 4";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens();
+
+        let mut parser = Parser::new(tokens.unwrap());
+        let parsed_expr = parser.expression();
+        let string_expr = parsed_expr.unwrap().to_string();
+
+        assert_eq!(string_expr, "(== (.. 0 (+ n 1) 2) (..= 1 (| 3 4)))");
+    }
+
+    #[test]
+    fn handle_classes_and_loops() {
+        let source = "class A { init(x) { this.x = x; } get() { return this.x; } }
+                      for ((k, v) in pairs) if (k) print v; else print k;
+                      while (a.b) a.b = a.b - 1;";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens();
+
+        let mut parser = Parser::new(tokens.unwrap());
+        let stmts = parser.parse_program().unwrap();
+        let strings: Vec<String> = stmts.iter().map(|stmt| stmt.to_string()).collect();
+
+        assert_eq!(
+            strings,
+            vec![
+                "(class A (fun init (x) (= (. this x) x)) (fun get () (return (. this x))))",
+                "(for (k v) pairs (if k (print v) (print k)))",
+                "(while (. a b) (= (. a b) (- (. a b) 1)))"
+            ]
+        );
+    }
+
+    #[test]
+    fn handle_generators() {
+        let source = "fun count() { var i = 0; while (true) { yield i; i += 1; } }
+                      fun f() { fun g() { yield; } return g; }";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens();
+
+        let mut parser = Parser::new(tokens.unwrap());
+        let stmts = parser.parse_program().unwrap();
+        let strings: Vec<String> = stmts.iter().map(|stmt| stmt.to_string()).collect();
+
+        assert_eq!(
+            strings,
+            vec![
+                "(fun* count () (var i 0) (while true (block (yield i) (+= i 1))))",
+                "(fun f () (fun* g () (yield nil)) (return g))"
+            ]
+        );
+
+        let mut scanner = Scanner::new("yield 1;");
+        let mut parser = Parser::new(scanner.scan_tokens().unwrap());
+        assert!(parser.parse_program().is_err());
+    }
+
+    #[test]
+    fn handle_comprehensions() {
+        let source = "[x * 2 for x in xs if x > 0] + [[y for y in row] for row in m]";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens();
+
+        let mut parser = Parser::new(tokens.unwrap());
+        let parsed_expr = parser.expression();
+        let string_expr = parsed_expr.unwrap().to_string();
+
+        assert_eq!(
+            string_expr,
+            "(+ (list (* x 2) (for x xs) (if (> x 0))) (list (list y (for y row)) (for row m)))"
+        );
+
+        let mut scanner = Scanner::new("{k: v for (k, v) in pairs}");
+        let mut parser = Parser::new(scanner.scan_tokens().unwrap());
+        assert_eq!(
+            parser.expression().unwrap().to_string(),
+            "(map (k v) (for (k v) pairs))"
+        );
+
+        let mut scanner = Scanner::new("[1, x for x in xs]");
+        let mut parser = Parser::new(scanner.scan_tokens().unwrap());
+        assert!(parser.expression().is_err());
+    }
+
+    #[test]
+    fn handle_lambdas() {
+        let source = "fun f(g) { return g; }
+                      f(fun (a, b) { return a + b; });
+                      var inc = x => x + 1;
+                      var add = (a, b) => { return a + b; };
+                      (() => {\"k\": 1}, (a), (a, b));";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens();
+
+        let mut parser = Parser::new(tokens.unwrap());
+        let stmts = parser.parse_program().unwrap();
+        let strings: Vec<String> = stmts.iter().map(|stmt| stmt.to_string()).collect();
+
+        assert_eq!(
+            strings,
+            vec![
+                "(fun f (g) (return g))",
+                "(call f (fun (a b) (return (+ a b))))",
+                "(var inc (fun (x) (return (+ x 1))))",
+                "(var add (fun (a b) (return (+ a b))))",
+                "(tuple (fun () (return (map (k 1)))) (group a) (tuple a b))"
+            ]
+        );
+    }
+
+    #[test]
+    fn handle_parameters_and_named_arguments() {
+        let source = "fun f(a, b = a * 2, ...rest) { return rest; }
+                      f(1, b: c ? 2 : 3);
+                      var g = (x = 1, ...ys) => ys;";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens();
+
+        let mut parser = Parser::new(tokens.unwrap());
+        let stmts = parser.parse_program().unwrap();
+        let strings: Vec<String> = stmts.iter().map(|stmt| stmt.to_string()).collect();
+
+        assert_eq!(
+            strings,
+            vec![
+                "(fun f (a (= b (* a 2)) ...rest) (return rest))",
+                "(call f 1 (: b (? c 2 3)))",
+                "(var g (fun ((= x 1) ...ys) (return ys)))"
+            ]
+        );
+
+        for source in [
+            "fun f(a, a) {}",
+            "fun f(...a, b) {}",
+            "fun f(a = 1, b) {}",
+            "f(a: 1, 2);",
+            "f(a: 1, a: 2);",
+        ] {
+            let mut scanner = Scanner::new(source);
+            let mut parser = Parser::new(scanner.scan_tokens().unwrap());
+            assert!(parser.parse_program().is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn handle_loop_control() {
+        let source = "outer: for (var i = 0; i < 3; i += 1) { for (x in xs) continue outer; }
+                      for (;;) break;
+                      while (a) { if (b) break; }";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens();
+
+        let mut parser = Parser::new(tokens.unwrap());
+        let stmts = parser.parse_program().unwrap();
+        let strings: Vec<String> = stmts.iter().map(|stmt| stmt.to_string()).collect();
+
+        assert_eq!(
+            strings,
+            vec![
+                "(block (var i 0) (label outer (while (< i 3) (block (for x xs (continue outer))) (+= i 1))))",
+                "(while true (break))",
+                "(while a (block (if b (break))))"
+            ]
+        );
+
+        for source in [
+            "break;",
+            "if (a) continue;",
+            "while (a) { fun f() { break; } }",
+            "inner: while (a) {} while (b) break inner;",
+            "label: print 1;",
+        ] {
+            let mut scanner = Scanner::new(source);
+            let mut parser = Parser::new(scanner.scan_tokens().unwrap());
+            assert!(parser.parse_program().is_err(), "{}", source);
+        }
+
+        let source = "while (true) { fun g() { break; } }
+                      a: while (true) { break b; }
+                      if (x) { return 1; yield 2; import \"m.king\" as m; }";
+        let mut parser = Parser::new(Scanner::new(source).scan_tokens().unwrap());
+        assert_eq!(
+            parser.parse_program().err().unwrap(),
+            "Can't use 'break' outside of a loop at line 1
+Undefined loop label 'b' at line 2
+Can't return from top-level code at line 3
+Can't yield from top-level code at line 3
+'import' must be at the top level of a file at line 3"
+        );
+    }
+
+    #[test]
+    fn handle_exceptions() {
+        let source = "try { throw \"a\"; } catch (e) { print e; } finally { x = 1; }
+                      try { f(); } finally {}";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens();
+
+        let mut parser = Parser::new(tokens.unwrap());
+        let stmts = parser.parse_program().unwrap();
+        let strings: Vec<String> = stmts.iter().map(|stmt| stmt.to_string()).collect();
+
+        assert_eq!(
+            strings,
+            vec![
+                "(try ((throw a)) (catch e (print e)) (finally (= x 1)))",
+                "(try ((call f)) (finally ))"
+            ]
+        );
+
+        let mut scanner = Scanner::new("try { f(); }");
+        let mut parser = Parser::new(scanner.scan_tokens().unwrap());
+        assert!(parser.parse_program().is_err());
+    }
+
+    #[test]
+    fn handle_match() {
+        let source = "match x { 0 => \"zero\", -1 => \"minus\", [a, ...rest] => a, (p, q) => p + q, {name: n, age} if n != \"\" => age, _ => nil };";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens();
+
+        let mut parser = Parser::new(tokens.unwrap());
+        let stmts = parser.parse_program().unwrap();
+
+        assert_eq!(
+            stmts[0].to_string(),
+            "(match x (0 => zero) (-1 => minus) ([a ...rest] => a) ((tuple p q) => (+ p q)) ({name n age age} (if (!= n )) => age) (_ => nil))"
+        );
+
+        let source =
+            "match x { n if n > limit => n, (a, b) if (a < b) => f((c) => c), v if v => w => w };";
+        let mut parser = Parser::new(Scanner::new(source).scan_tokens().unwrap());
+        assert_eq!(
+            parser.parse_program().unwrap()[0].to_string(),
+            "(match x (n (if (> n limit)) => n) ((tuple a b) (if (group (< a b))) => (call f (fun (c) (return c)))) (v (if v) => (fun (w) (return w))))"
+        );
+
+        for source in [
+            "match x { [a, a] => a };",
+            "match x { };",
+            "match x { 1 + 2 => 3 };",
+        ] {
+            let mut scanner = Scanner::new(source);
+            let mut parser = Parser::new(scanner.scan_tokens().unwrap());
+            assert!(parser.parse_program().is_err());
+        }
+    }
+
+    #[test]
+    fn handle_switch() {
+        let source =
+            "switch (x) { case 1: print \"one\"; break; case 2, -3: case 4: default: y = 0; }";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens();
+
+        let mut parser = Parser::new(tokens.unwrap());
+        let stmts = parser.parse_program().unwrap();
+
+        assert_eq!(
+            stmts[0].to_string(),
+            "(switch x (case 1 (print one) (break)) (case 2 (- 3)) (case 4) (default (= y 0)))"
+        );
+
+        for source in [
+            "switch (x) { case 1: case 2, 1: }",
+            "switch (x) { case \"a\": case (\"a\"): }",
+            "switch (x) { default: default: }",
+            "switch (x) { print x; }",
+            "switch (x) { case 1: continue; }",
+            "switch (x) { case 1: fun f() { break; } }",
+        ] {
+            let mut scanner = Scanner::new(source);
+            let mut parser = Parser::new(scanner.scan_tokens().unwrap());
+            assert!(parser.parse_program().is_err());
+        }
+
+        let source = "fun f(x) {
+                          switch (x) { case 1: x = 2; case 1.0: print x; default: default: }
+                          return x;
+                      }
+                      switch (1) { case \"a\", \"a\": break; }";
+        let mut scanner = Scanner::new(source);
+        let mut parser = Parser::new(scanner.scan_tokens().unwrap());
+        assert_eq!(
+            parser.parse_program().err().unwrap(),
+            "Duplicate case 1 in switch at line 2
+Duplicate default in switch at line 2
+Duplicate case \"a\" in switch at line 5"
+        );
+    }
+
+    #[test]
+    fn handle_constants() {
+        let source = "const limit = 10;
+                      { var limit = 1; limit = 2; }
+                      fun f(limit) { limit += 1; }
+                      for (limit in [1]) { limit++; }
+                      match 1 { limit => limit = 0 };";
+        let mut scanner = Scanner::new(source);
+        let mut parser = Parser::new(scanner.scan_tokens().unwrap());
+        let stmts = parser.parse_program().unwrap();
+        assert_eq!(stmts[0].to_string(), "(const limit 10)");
+
+        let parse = |source: &str| {
+            let mut scanner = Scanner::new(source);
+            let mut parser = Parser::new(scanner.scan_tokens().unwrap());
+            parser.parse_program().err()
+        };
+        assert_eq!(
+            parse("const limit = 10;\n\nlimit = 11;").unwrap(),
+            "Can't assign to constant 'limit' at line 3 (declared at line 1)"
+        );
+        assert_eq!(
+            parse("const a = 1;\nvar a = 2;").unwrap(),
+            "Can't redeclare constant 'a' at line 2 (declared at line 1)"
+        );
+        assert!(parse("const a = 1; fun f() { a += 1; }").is_some());
+        assert!(parse("const a = 1; { a++; }").is_some());
+        assert!(parse("const a = 1; var b; (a, b) = (2, 3);").is_some());
+        assert!(parse("const a;").is_some());
+        assert_eq!(
+            parse("const a = 1;\nfun f() {\n  a = 2;\n  return a;\n}\n{ a++; var a = 3; }")
+                .unwrap(),
+            "Can't assign to constant 'a' at line 3 (declared at line 1)
+Can't assign to constant 'a' at line 6 (declared at line 1)"
+        );
+    }
+
+    #[test]
+    fn handle_enums() {
+        let source = "enum State { Loading, Loaded(data), Failed(error, retries), }
+                      match s { State.Loading => 0, State.Loaded(d) => d, State.Failed => 1 };";
+        let mut scanner = Scanner::new(source);
+        let mut parser = Parser::new(scanner.scan_tokens().unwrap());
+        let stmts = parser.parse_program().unwrap();
+        let strings: Vec<String> = stmts.iter().map(|stmt| stmt.to_string()).collect();
+
+        assert_eq!(
+            strings,
+            vec![
+                "(enum State Loading (Loaded data) (Failed error retries))",
+                "(match s ((. State Loading) => 0) (((. State Loaded) d) => d) ((. State Failed) => 1))"
+            ]
+        );
+
+        for source in ["enum A { X, X }", "enum A { X(a, a) }", "enum A { X(1) }"] {
+            let mut scanner = Scanner::new(source);
+            let mut parser = Parser::new(scanner.scan_tokens().unwrap());
+            assert!(parser.parse_program().is_err());
+        }
+    }
+
+    #[test]
+    fn handle_traits() {
+        let source = "trait Shape { area(); describe() { return this.area(); } }
+                      class Square impl Shape, Named { area() { return 4; } }
+                      s is Shape == true;";
+        let mut scanner = Scanner::new(source);
+        let mut parser = Parser::new(scanner.scan_tokens().unwrap());
+        let stmts = parser.parse_program().unwrap();
+        let strings: Vec<String> = stmts.iter().map(|stmt| stmt.to_string()).collect();
+
+        assert_eq!(
+            strings,
+            vec![
+                "(trait Shape (area) (fun describe () (return (call (. this area)))))",
+                "(class Square (impl Shape Named) (fun area () (return 4)))",
+                "(== (is s Shape) true)"
+            ]
+        );
+
+        for source in [
+            "trait T { a(); a() {} }",
+            "trait T { a() }",
+            "class C impl {}",
+        ] {
+            let mut scanner = Scanner::new(source);
+            let mut parser = Parser::new(scanner.scan_tokens().unwrap());
+            assert!(parser.parse_program().is_err());
+        }
+    }
+
+    #[test]
+    fn handle_static_methods_and_accessors() {
+        let source = "class Shape {
+                          static unit() { return this(); }
+                          get area { return 1; }
+                          set area(value) { this._area = value; }
+                          get(key) { return key; }
+                      }";
+        let mut scanner = Scanner::new(source);
+        let mut parser = Parser::new(scanner.scan_tokens().unwrap());
+        let stmts = parser.parse_program().unwrap();
+
+        assert_eq!(
+            stmts[0].to_string(),
+            "(class Shape (fun get (key) (return key)) (static (fun unit () (return (call this)))) (get (fun area () (return 1))) (set (fun area (value) (= (. this _area) value))))"
+        );
+
+        for source in [
+            "class A { set x() {} }",
+            "class A { set x(a, b) {} }",
+            "class A { get x() {} }",
+            "class A { public x() {} }",
+        ] {
+            let mut scanner = Scanner::new(source);
+            let mut parser = Parser::new(scanner.scan_tokens().unwrap());
+            assert!(parser.parse_program().is_err());
+        }
+    }
+
+    #[test]
+    fn handle_modules() {
+        let source = "import \"lib/math.king\" as math;
+                      from \"shapes.king\" import Circle, area;
+                      export const PI = 3;
+                      export fun double(x) { return x * 2; }";
+        let mut scanner = Scanner::new(source);
+        let mut parser = Parser::new(scanner.scan_tokens().unwrap());
+        let stmts = parser.parse_program().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(import \"lib/math.king\" as math)");
+        assert_eq!(
+            stmts[1].to_string(),
+            "(from \"shapes.king\" import Circle area)"
+        );
+        assert_eq!(stmts[2].to_string(), "(export (const PI 3))");
+        assert_eq!(
+            stmts[3].to_string(),
+            "(export (fun double (x) (return (* x 2))))"
+        );
+
+        for source in [
+            "import \"a.king\";",
+            "import a as b;",
+            "from \"a.king\" import a, a;",
+            "export print 1;",
+            "fun f() { import \"a.king\" as a; }",
+            "{ export var a = 1; }",
+            "const PI = 1; from \"a.king\" import PI;",
+        ] {
+            let mut scanner = Scanner::new(source);
+            let mut parser = Parser::new(scanner.scan_tokens().unwrap());
+            assert!(parser.parse_program().is_err());
+        }
+    }
+
+    #[test]
+    fn handle_bitwise_precedence() {
+        let source = "1 | 2 ^ 3 & 4 << 1 + 1 == ~5";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens();
+
+        let mut parser = Parser::new(tokens.unwrap());
+        let parsed_expr = parser.expression();
+        let string_expr = parsed_expr.unwrap().to_string();
+
+        assert_eq!(string_expr, "(== (| 1 (^ 2 (& 3 (<< 4 (+ 1 1))))) (~ 5))");
+    }
 }
@@ -0,0 +1,227 @@
+use crate::environment::Environment;
+use crate::error::RuntimeError;
+use crate::expr::{self, Expr, LiteralValue, Shared};
+use crate::scanner::Token;
+use std::{cell::RefCell, rc::Rc};
+
+/// A pattern in a `match` arm, tested against a value's shape.
+pub enum Pattern {
+    /// `_`, matching anything without binding it.
+    Wildcard,
+    Literal(LiteralValue),
+    /// A name, matching anything and binding it.
+    Binding(Token),
+    /// `[a, b]`, or `[first, ...rest]` to bind the remaining items.
+    List {
+        elements: Vec<Pattern>,
+        rest: Option<Token>,
+    },
+    Tuple(Vec<Pattern>),
+    /// `{name: n, "key": k}`, matching maps that have the keys and instances
+    /// that have the fields. Other keys are ignored.
+    Map(Vec<(LiteralValue, Pattern)>),
+    /// `Shape.Circle(r)`, matching values of an enum variant and their
+    /// fields, or `Shape.Circle` alone to match any of them.
+    Variant {
+        constructor: Expr,
+        fields: Option<Vec<Pattern>>,
+    },
+}
+
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expr>,
+    pub body: Expr,
+}
+
+impl Pattern {
+    /// Tests `value` against this pattern, collecting the bound names into
+    /// `bindings`. Variant patterns look their enum up in `environment`.
+    pub fn matches(
+        self: &Self,
+        value: &LiteralValue,
+        bindings: &mut Vec<(String, LiteralValue)>,
+        environment: &Rc<RefCell<Environment>>,
+    ) -> Result<bool, RuntimeError> {
+        let matched = match (self, value) {
+            (Pattern::Wildcard, _) => true,
+            (Pattern::Literal(literal), value) => expr::values_equal(literal, value)?,
+            (Pattern::Binding(name), value) => {
+                bindings.push((name.lexeme.clone(), value.clone()));
+                true
+            }
+            (
+                Pattern::Variant {
+                    constructor,
+                    fields,
+                },
+                value,
+            ) => return Self::matches_variant(constructor, fields, value, bindings, environment),
+            (Pattern::List { elements, rest }, LiteralValue::List(items)) => {
+                let items = items.borrow();
+                let fits = match rest {
+                    Some(_) => items.len() >= elements.len(),
+                    None => items.len() == elements.len(),
+                };
+                if !fits || !Self::matches_all(elements, &items, bindings, environment)? {
+                    return Ok(false);
+                }
+
+                if let Some(rest) = rest {
+                    let remaining = items[elements.len()..].to_vec();
+                    bindings.push((
+                        rest.lexeme.clone(),
+                        LiteralValue::List(Rc::new(Shared::new(remaining))),
+                    ));
+                }
+                true
+            }
+            (Pattern::Tuple(elements), LiteralValue::Tuple(items)) => {
+                items.len() == elements.len()
+                    && Self::matches_all(elements, items, bindings, environment)?
+            }
+            (Pattern::Map(entries), LiteralValue::Map(map)) => {
+                let values = {
+                    let map = map.borrow();
+                    entries
+                        .iter()
+                        .map(|(key, _)| map.get(key).ok().flatten())
+                        .collect()
+                };
+                return Self::matches_entries(entries, values, bindings, environment);
+            }
+            (Pattern::Map(entries), LiteralValue::Instance(instance)) => {
+                let values = {
+                    let instance = instance.borrow();
+                    entries
+                        .iter()
+                        .map(|(key, _)| match key {
+                            LiteralValue::StringValue(name) => instance.fields.get(name).cloned(),
+                            _ => None,
+                        })
+                        .collect()
+                };
+                return Self::matches_entries(entries, values, bindings, environment);
+            }
+            _ => false,
+        };
+
+        Ok(matched)
+    }
+
+    fn matches_all(
+        patterns: &[Pattern],
+        items: &[LiteralValue],
+        bindings: &mut Vec<(String, LiteralValue)>,
+        environment: &Rc<RefCell<Environment>>,
+    ) -> Result<bool, RuntimeError> {
+        for (pattern, item) in patterns.iter().zip(items) {
+            if !pattern.matches(item, bindings, environment)? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Matches the pattern of each entry against the value found for its
+    /// key, failing if any key was missing.
+    fn matches_entries(
+        entries: &[(LiteralValue, Pattern)],
+        values: Vec<Option<LiteralValue>>,
+        bindings: &mut Vec<(String, LiteralValue)>,
+        environment: &Rc<RefCell<Environment>>,
+    ) -> Result<bool, RuntimeError> {
+        for ((_, pattern), value) in entries.iter().zip(values) {
+            match value {
+                Some(value) if pattern.matches(&value, bindings, environment)? => (),
+                _ => return Ok(false),
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn matches_variant(
+        constructor: &Expr,
+        fields: &Option<Vec<Pattern>>,
+        value: &LiteralValue,
+        bindings: &mut Vec<(String, LiteralValue)>,
+        environment: &Rc<RefCell<Environment>>,
+    ) -> Result<bool, RuntimeError> {
+        let (enum_type, variant) = match constructor.evaluate(environment.clone())? {
+            LiteralValue::Variant(enum_type, variant) => (enum_type, variant),
+            expected @ LiteralValue::EnumValue(_) if fields.is_none() => {
+                return expr::values_equal(&expected, value)
+            }
+            other => {
+                return Err(RuntimeError::new(
+                    "TypeError",
+                    format!(
+                        "Expected an enum variant in pattern, got {}",
+                        other.to_string()
+                    ),
+                ))
+            }
+        };
+
+        let value = match value {
+            LiteralValue::EnumValue(value) if value.same_variant(&enum_type, variant) => value,
+            _ => return Ok(false),
+        };
+        match fields {
+            Some(fields) if fields.len() != value.values.len() => Err(RuntimeError::new(
+                "TypeError",
+                format!(
+                    "Pattern for {}.{} expects {} fields but got {}",
+                    enum_type.name,
+                    value.variant().name,
+                    value.values.len(),
+                    fields.len()
+                ),
+            )),
+            Some(fields) => Self::matches_all(fields, &value.values, bindings, environment),
+            None => Ok(true),
+        }
+    }
+
+    pub fn to_string(self: &Self) -> String {
+        match self {
+            Pattern::Wildcard => "_".to_string(),
+            Pattern::Literal(value) => value.to_string(),
+            Pattern::Binding(name) => name.lexeme.clone(),
+            Pattern::List { elements, rest } => {
+                let mut parts: Vec<String> = elements.iter().map(|p| p.to_string()).collect();
+                if let Some(rest) = rest {
+                    parts.push(format!("...{}", rest.lexeme));
+                }
+                format!("[{}]", parts.join(" "))
+            }
+            Pattern::Tuple(elements) => {
+                let mut parts = vec!["tuple".to_string()];
+                parts.extend(elements.iter().map(|p| p.to_string()));
+                format!("({})", parts.join(" "))
+            }
+            Pattern::Variant {
+                constructor,
+                fields: Some(fields),
+            } => {
+                let mut parts = vec![constructor.to_string()];
+                parts.extend(fields.iter().map(|p| p.to_string()));
+                format!("({})", parts.join(" "))
+            }
+            Pattern::Variant {
+                constructor,
+                fields: None,
+            } => constructor.to_string(),
+            Pattern::Map(entries) => format!(
+                "{{{}}}",
+                entries
+                    .iter()
+                    .map(|(key, pattern)| format!("{} {}", key.to_string(), pattern.to_string()))
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            ),
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use crate::expr::LiteralValue;
+
+/// A lazily evaluated arithmetic sequence created by `a..b` or `a..=b`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Range {
+    pub start: f32,
+    pub end: f32,
+    pub step: f32,
+    pub inclusive: bool,
+}
+
+impl Range {
+    pub fn new(start: f32, end: f32, step: f32, inclusive: bool) -> Result<Self, String> {
+        if step == 0.0 || !step.is_finite() {
+            return Err(format!(
+                "Range step must be a non-zero number, got {}",
+                step
+            ));
+        }
+
+        Ok(Self {
+            start,
+            end,
+            step,
+            inclusive,
+        })
+    }
+
+    pub fn len(self: &Self) -> usize {
+        let steps = (self.end - self.start) / self.step;
+        if steps < 0.0 {
+            return 0;
+        }
+
+        if self.inclusive {
+            steps.floor() as usize + 1
+        } else {
+            steps.ceil() as usize
+        }
+    }
+
+    pub fn is_empty(self: &Self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(self: &Self, i: usize) -> f32 {
+        self.start + i as f32 * self.step
+    }
+
+    pub fn contains(self: &Self, value: &LiteralValue) -> bool {
+        match value {
+            LiteralValue::Number(x) => {
+                let steps = (x - self.start) / self.step;
+                steps.fract() == 0.0 && steps >= 0.0 && (steps as usize) < self.len()
+            }
+            _ => false,
+        }
+    }
+
+    pub fn to_string(self: &Self) -> String {
+        let operator = if self.inclusive { "..=" } else { ".." };
+        if self.step == 1.0 {
+            format!("{}{}{}", self.start, operator, self.end)
+        } else {
+            format!("{}{}{} step {}", self.start, operator, self.end, self.step)
+        }
+    }
+}
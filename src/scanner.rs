@@ -1,12 +1,11 @@
 use std::{collections::HashMap, string::String};
 
 fn is_digit(ch: char) -> bool {
-    (ch as u8) >= '0' as u8 && (ch as u8) <= '9' as u8
+    ch.is_ascii_digit()
 }
 
 fn is_alpha(ch: char) -> bool {
-    let uch = ch as u8;
-    (uch >= 'a' as u8 && uch <= 'z' as u8) || (uch >= 'A' as u8 && uch <= 'Z' as u8) || (ch == '_')
+    ch.is_ascii_alphabetic() || ch == '_'
 }
 
 fn is_alpha_numeric(ch: char) -> bool {
@@ -16,21 +15,40 @@ fn is_alpha_numeric(ch: char) -> bool {
 fn get_keywords_hashmap() -> HashMap<&'static str, TokenType> {
     HashMap::from([
         ("and", And),
+        ("break", Break),
+        ("case", Case),
+        ("catch", Catch),
         ("class", Class),
+        ("const", Const),
+        ("continue", Continue),
+        ("default", Default),
         ("else", Else),
+        ("enum", Enum),
+        ("export", Export),
         ("false", False),
+        ("finally", Finally),
         ("for", For),
         ("fun", Fun),
         ("if", If),
+        ("impl", Impl),
+        ("import", Import),
+        ("in", In),
+        ("is", Is),
+        ("match", Match),
         ("nil", Nil),
         ("or", Or),
         ("print", Print),
         ("return", Return),
         ("super", Super),
+        ("switch", Switch),
         ("this", This),
+        ("throw", Throw),
+        ("trait", Trait),
         ("true", True),
+        ("try", Try),
         ("var", Var),
         ("while", While),
+        ("yield", Yield),
     ])
 }
 
@@ -74,11 +92,11 @@ impl Scanner {
             line_number: self.line,
         });
 
-        if errors.len() > 0 {
+        if !errors.is_empty() {
             let mut joined_errors = "".to_string();
             for error in errors {
                 joined_errors.push_str(&error);
-                joined_errors.push_str("\n");
+                joined_errors.push('\n');
             }
             return Err(joined_errors);
         }
@@ -98,12 +116,82 @@ impl Scanner {
             ')' => self.add_token(RightParen),
             '{' => self.add_token(LeftBrace),
             '}' => self.add_token(RightBrace),
+            '[' => self.add_token(LeftBracket),
+            ']' => self.add_token(RightBracket),
             ',' => self.add_token(Comma),
-            '.' => self.add_token(Dot),
-            '-' => self.add_token(Minus),
-            '+' => self.add_token(Plus),
+            '.' => {
+                let token = if self.char_match('.') {
+                    if self.char_match('=') {
+                        DotDotEqual
+                    } else if self.char_match('.') {
+                        DotDotDot
+                    } else {
+                        DotDot
+                    }
+                } else {
+                    Dot
+                };
+                self.add_token(token);
+            }
+            '-' => {
+                let token = if self.char_match('=') {
+                    MinusEqual
+                } else if self.char_match('-') {
+                    MinusMinus
+                } else {
+                    Minus
+                };
+                self.add_token(token);
+            }
+            '+' => {
+                let token = if self.char_match('=') {
+                    PlusEqual
+                } else if self.char_match('+') {
+                    PlusPlus
+                } else {
+                    Plus
+                };
+                self.add_token(token);
+            }
             ';' => self.add_token(Semicolon),
-            '*' => self.add_token(Star),
+            '?' => {
+                let token = if self.char_match('?') {
+                    QuestionQuestion
+                } else if self.char_match('.') {
+                    QuestionDot
+                } else {
+                    Question
+                };
+                self.add_token(token);
+            }
+            ':' => self.add_token(Colon),
+            '*' => {
+                let token = if self.char_match('=') {
+                    StarEqual
+                } else {
+                    Star
+                };
+                self.add_token(token);
+            }
+            '%' => {
+                let token = if self.char_match('=') {
+                    PercentEqual
+                } else {
+                    Percent
+                };
+                self.add_token(token);
+            }
+            '&' => self.add_token(Ampersand),
+            '|' => {
+                let token = if self.char_match('>') {
+                    PipeGreater
+                } else {
+                    Pipe
+                };
+                self.add_token(token);
+            }
+            '^' => self.add_token(Caret),
+            '~' => self.add_token(Tilde),
             '!' => {
                 let token = if self.char_match('=') {
                     BangEqual
@@ -115,6 +203,8 @@ impl Scanner {
             '=' => {
                 let token = if self.char_match('=') {
                     EqualEqual
+                } else if self.char_match('>') {
+                    EqualGreater
                 } else {
                     Equal
                 };
@@ -123,6 +213,8 @@ impl Scanner {
             '<' => {
                 let token = if self.char_match('=') {
                     LessEqual
+                } else if self.char_match('<') {
+                    LessLess
                 } else {
                     Less
                 };
@@ -131,6 +223,8 @@ impl Scanner {
             '>' => {
                 let token = if self.char_match('=') {
                     GreaterEqual
+                } else if self.char_match('>') {
+                    GreaterGreater
                 } else {
                     Greater
                 };
@@ -144,6 +238,8 @@ impl Scanner {
                         }
                         self.advance();
                     }
+                } else if self.char_match('=') {
+                    self.add_token(SlashEqual);
                 } else {
                     self.add_token(Slash);
                 }
@@ -153,7 +249,7 @@ impl Scanner {
             '"' => self.string()?,
             c => {
                 if is_digit(c) {
-                    let _ = self.number()?;
+                    self.number()?;
                 } else if is_alpha(c) {
                     self.identifier()
                 } else {
@@ -256,7 +352,7 @@ impl Scanner {
         let c = self.source.chars().nth(self.current).unwrap();
         self.current += 1;
 
-        c as char
+        c
     }
 
     fn add_token(self: &mut Self, token_type: TokenType) {
@@ -267,9 +363,9 @@ impl Scanner {
         let text = self.source[self.start..self.current].to_string();
 
         self.tokens.push(Token {
-            token_type: token_type,
+            token_type,
             lexeme: text,
-            literal: literal,
+            literal,
             line_number: self.line,
         });
     }
@@ -282,24 +378,49 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
     Dot,
     Minus,
     Plus,
     Semicolon,
+    Question,
+    Colon,
     Slash,
     Star,
+    Percent,
     Not,
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
 
     //one or two chars
     Bang,
     BangEqual,
     Equal,
     EqualEqual,
+    EqualGreater,
     Greater,
     GreaterEqual,
     Less,
     LessEqual,
+    LessLess,
+    GreaterGreater,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    PercentEqual,
+    PlusPlus,
+    MinusMinus,
+    QuestionQuestion,
+    QuestionDot,
+    PipeGreater,
+    DotDot,
+    DotDotEqual,
+    DotDotDot,
 
     //literals
     Identifier,
@@ -308,21 +429,40 @@ pub enum TokenType {
 
     //keywords
     And,
+    Break,
+    Case,
+    Catch,
     Class,
+    Const,
+    Continue,
+    Default,
     Else,
+    Enum,
+    Export,
     False,
+    Finally,
     Fun,
     For,
     If,
+    Impl,
+    Import,
+    In,
+    Is,
+    Match,
     Nil,
     Or,
     Print,
     Return,
     Super,
+    Switch,
     This,
+    Throw,
+    Trait,
     True,
+    Try,
     Var,
     While,
+    Yield,
 
     Eof,
 }
@@ -390,6 +530,18 @@ mod tests {
         assert_eq!(scanner.tokens[4].token_type, Eof);
     }
 
+    #[test]
+    fn handle_bracket_tokens() {
+        let source = "][";
+        let mut scanner = Scanner::new(source);
+        let _ = scanner.scan_tokens();
+
+        assert_eq!(scanner.tokens.len(), 3);
+        assert_eq!(scanner.tokens[0].token_type, RightBracket);
+        assert_eq!(scanner.tokens[1].token_type, LeftBracket);
+        assert_eq!(scanner.tokens[2].token_type, Eof);
+    }
+
     #[test]
     fn handle_two_char_tokens() {
         let source = "= != == >=";
@@ -404,6 +556,83 @@ mod tests {
         assert_eq!(scanner.tokens[4].token_type, Eof);
     }
 
+    #[test]
+    fn handle_bitwise_tokens() {
+        let source = "& | ^ ~ << >> <= > |>";
+        let mut scanner = Scanner::new(source);
+        let _ = scanner.scan_tokens();
+
+        assert_eq!(scanner.tokens.len(), 10);
+        assert_eq!(scanner.tokens[0].token_type, Ampersand);
+        assert_eq!(scanner.tokens[1].token_type, Pipe);
+        assert_eq!(scanner.tokens[2].token_type, Caret);
+        assert_eq!(scanner.tokens[3].token_type, Tilde);
+        assert_eq!(scanner.tokens[4].token_type, LessLess);
+        assert_eq!(scanner.tokens[5].token_type, GreaterGreater);
+        assert_eq!(scanner.tokens[6].token_type, LessEqual);
+        assert_eq!(scanner.tokens[7].token_type, Greater);
+        assert_eq!(scanner.tokens[8].token_type, PipeGreater);
+        assert_eq!(scanner.tokens[9].token_type, Eof);
+    }
+
+    #[test]
+    fn handle_compound_assignment_tokens() {
+        let source = "+= -= *= /= %= ++ -- % // comment";
+        let mut scanner = Scanner::new(source);
+        let _ = scanner.scan_tokens();
+
+        assert_eq!(scanner.tokens.len(), 9);
+        assert_eq!(scanner.tokens[0].token_type, PlusEqual);
+        assert_eq!(scanner.tokens[1].token_type, MinusEqual);
+        assert_eq!(scanner.tokens[2].token_type, StarEqual);
+        assert_eq!(scanner.tokens[3].token_type, SlashEqual);
+        assert_eq!(scanner.tokens[4].token_type, PercentEqual);
+        assert_eq!(scanner.tokens[5].token_type, PlusPlus);
+        assert_eq!(scanner.tokens[6].token_type, MinusMinus);
+        assert_eq!(scanner.tokens[7].token_type, Percent);
+        assert_eq!(scanner.tokens[8].token_type, Eof);
+    }
+
+    #[test]
+    fn handle_question_tokens() {
+        let source = "a ?? b?.c ? d : e";
+        let mut scanner = Scanner::new(source);
+        let _ = scanner.scan_tokens();
+
+        assert_eq!(scanner.tokens.len(), 10);
+        assert_eq!(scanner.tokens[1].token_type, QuestionQuestion);
+        assert_eq!(scanner.tokens[3].token_type, QuestionDot);
+        assert_eq!(scanner.tokens[5].token_type, Question);
+        assert_eq!(scanner.tokens[7].token_type, Colon);
+    }
+
+    #[test]
+    fn handle_arrow_tokens() {
+        let source = "(a) => a == b = c";
+        let mut scanner = Scanner::new(source);
+        let _ = scanner.scan_tokens();
+
+        assert_eq!(scanner.tokens.len(), 10);
+        assert_eq!(scanner.tokens[3].token_type, EqualGreater);
+        assert_eq!(scanner.tokens[5].token_type, EqualEqual);
+        assert_eq!(scanner.tokens[7].token_type, Equal);
+    }
+
+    #[test]
+    fn handle_range_tokens() {
+        let source = "1..5 0..=n a.b ...rest";
+        let mut scanner = Scanner::new(source);
+        let _ = scanner.scan_tokens();
+
+        assert_eq!(scanner.tokens.len(), 12);
+        assert_eq!(scanner.tokens[9].token_type, DotDotDot);
+        assert_eq!(scanner.tokens[0].token_type, Number);
+        assert_eq!(scanner.tokens[1].token_type, DotDot);
+        assert_eq!(scanner.tokens[2].token_type, Number);
+        assert_eq!(scanner.tokens[4].token_type, DotDotEqual);
+        assert_eq!(scanner.tokens[7].token_type, Dot);
+    }
+
     #[test]
     fn handle_string_literal() {
         let source = r#""ABC""#;
@@ -434,7 +663,7 @@ mod tests {
     fn handle_string_literal_multiline() {
         let source = "\"ABC\ndef\"";
         let mut scanner = Scanner::new(source);
-        let _ = scanner.scan_token().unwrap();
+        scanner.scan_token().unwrap();
 
         assert_eq!(scanner.tokens.len(), 1);
         assert_eq!(scanner.tokens[0].token_type, StringKing);
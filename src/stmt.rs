@@ -0,0 +1,414 @@
+use crate::expr::Expr;
+use crate::scanner::Token;
+use std::rc::Rc;
+
+/// A function parameter: `a`, `b = 10` or the variadic `...rest`.
+pub struct Param {
+    pub name: Token,
+    pub default: Option<Expr>,
+    pub variadic: bool,
+}
+
+impl Param {
+    pub fn to_string(self: &Self) -> String {
+        match &self.default {
+            Some(default) => format!("(= {} {})", self.name.lexeme, default.to_string()),
+            None if self.variadic => format!("...{}", self.name.lexeme),
+            None => self.name.lexeme.clone(),
+        }
+    }
+}
+
+/// One `case 1, 2:` or `default:` label of a switch. `start` is the index in
+/// the switch body of the first statement under the label.
+pub struct SwitchCase {
+    pub keyword: Token,
+    /// The values compared against the subject; empty for `default`.
+    pub values: Vec<Expr>,
+    pub start: usize,
+}
+
+pub enum Stmt {
+    Expression {
+        expression: Expr,
+    },
+    Print {
+        keyword: Token,
+        expression: Expr,
+    },
+    Var {
+        name: Token,
+        initializer: Expr,
+    },
+    /// `const NAME = value;`, which the parser rejects assignments to.
+    Const {
+        name: Token,
+        initializer: Expr,
+    },
+    Destructure {
+        names: Vec<Token>,
+        paren: Token,
+        initializer: Expr,
+    },
+    Block {
+        statements: Vec<Stmt>,
+    },
+    Function {
+        name: Token,
+        params: Rc<Vec<Param>>,
+        body: Rc<Vec<Stmt>>,
+        is_generator: bool,
+    },
+    Class {
+        name: Token,
+        traits: Vec<Token>,
+        methods: Vec<Stmt>,
+        statics: Vec<Stmt>,
+        getters: Vec<Stmt>,
+        setters: Vec<Stmt>,
+    },
+    /// `trait Name { required(); with_default() { ... } }`.
+    Trait {
+        name: Token,
+        required: Vec<Token>,
+        methods: Vec<Stmt>,
+    },
+    /// `enum Name { Plain, WithFields(a, b) }`: each variant and its fields.
+    Enum {
+        name: Token,
+        variants: Vec<(Token, Vec<Token>)>,
+    },
+    /// `import "path" as name;`, or `from "path" import a, b;` when `names`
+    /// is not empty.
+    Import {
+        keyword: Token,
+        path: String,
+        alias: Option<Token>,
+        names: Vec<Token>,
+    },
+    /// A top-level declaration whose names other files can import.
+    Export {
+        keyword: Token,
+        declaration: Box<Stmt>,
+    },
+    If {
+        condition: Expr,
+        then_branch: Box<Stmt>,
+        else_branch: Option<Box<Stmt>>,
+    },
+    /// A `while` loop, or a C-style `for` loop when `increment` is set so
+    /// that it still runs after a `continue`.
+    While {
+        label: Option<Token>,
+        condition: Expr,
+        body: Box<Stmt>,
+        increment: Option<Expr>,
+    },
+    ForIn {
+        label: Option<Token>,
+        keyword: Token,
+        names: Vec<Token>,
+        paren: Option<Token>,
+        iterable: Expr,
+        body: Box<Stmt>,
+    },
+    Return {
+        keyword: Token,
+        value: Expr,
+    },
+    Throw {
+        keyword: Token,
+        value: Expr,
+    },
+    /// `try { } catch (name) { } finally { }`, where at least one of the
+    /// `catch` and `finally` clauses is present.
+    Try {
+        body: Vec<Stmt>,
+        catch: Option<(Token, Vec<Stmt>)>,
+        finally: Option<Vec<Stmt>>,
+    },
+    /// The statements of every case share one body, so that execution can
+    /// fall through from a case into the next until a `break`.
+    Switch {
+        keyword: Token,
+        subject: Expr,
+        cases: Vec<SwitchCase>,
+        body: Vec<Stmt>,
+    },
+    Break {
+        keyword: Token,
+        label: Option<Token>,
+    },
+    Continue {
+        keyword: Token,
+        label: Option<Token>,
+    },
+    Yield {
+        keyword: Token,
+        value: Expr,
+    },
+}
+
+impl Stmt {
+    /// The names a declaration defines, which `export` makes importable.
+    pub fn declared_names(self: &Self) -> Vec<&Token> {
+        match self {
+            Stmt::Var { name, .. }
+            | Stmt::Const { name, .. }
+            | Stmt::Function { name, .. }
+            | Stmt::Class { name, .. }
+            | Stmt::Trait { name, .. }
+            | Stmt::Enum { name, .. } => vec![name],
+            Stmt::Destructure { names, .. } => names.iter().collect(),
+            _ => vec![],
+        }
+    }
+
+    pub fn to_string(self: &Self) -> String {
+        match self {
+            Stmt::Expression { expression } => expression.to_string(),
+            Stmt::Print {
+                keyword: _,
+                expression,
+            } => format!("(print {})", expression.to_string()),
+            Stmt::Var { name, initializer } => {
+                format!("(var {} {})", name.lexeme, initializer.to_string())
+            }
+            Stmt::Const { name, initializer } => {
+                format!("(const {} {})", name.lexeme, initializer.to_string())
+            }
+            Stmt::Destructure {
+                names,
+                paren: _,
+                initializer,
+            } => format!(
+                "(var ({}) {})",
+                names
+                    .iter()
+                    .map(|name| name.lexeme.clone())
+                    .collect::<Vec<String>>()
+                    .join(" "),
+                initializer.to_string()
+            ),
+            Stmt::Block { statements } => format!(
+                "(block {})",
+                statements
+                    .iter()
+                    .map(|stmt| stmt.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            ),
+            Stmt::Function {
+                name,
+                params,
+                body,
+                is_generator,
+            } => format!(
+                "({} {} ({}) {})",
+                if *is_generator { "fun*" } else { "fun" },
+                name.lexeme,
+                params
+                    .iter()
+                    .map(|param| param.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" "),
+                body.iter()
+                    .map(|stmt| stmt.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            ),
+            Stmt::Class {
+                name,
+                traits,
+                methods,
+                statics,
+                getters,
+                setters,
+            } => {
+                let mut parts = vec![format!("class {}", name.lexeme)];
+                if !traits.is_empty() {
+                    let mut names = vec!["impl".to_string()];
+                    names.extend(traits.iter().map(|name| name.lexeme.clone()));
+                    parts.push(format!("({})", names.join(" ")));
+                }
+                parts.push(
+                    methods
+                        .iter()
+                        .map(|method| method.to_string())
+                        .collect::<Vec<String>>()
+                        .join(" "),
+                );
+                for (kind, members) in [("static", statics), ("get", getters), ("set", setters)] {
+                    parts.extend(
+                        members
+                            .iter()
+                            .map(|member| format!("({} {})", kind, member.to_string())),
+                    );
+                }
+                format!("({})", parts.join(" "))
+            }
+            Stmt::Trait {
+                name,
+                required,
+                methods,
+            } => {
+                let mut parts = vec![format!("trait {}", name.lexeme)];
+                parts.extend(required.iter().map(|name| format!("({})", name.lexeme)));
+                parts.extend(methods.iter().map(|method| method.to_string()));
+                format!("({})", parts.join(" "))
+            }
+            Stmt::Enum { name, variants } => {
+                let mut parts = vec![format!("enum {}", name.lexeme)];
+                parts.extend(variants.iter().map(|(variant, fields)| {
+                    if fields.is_empty() {
+                        return variant.lexeme.clone();
+                    }
+                    let mut names = vec![variant.lexeme.clone()];
+                    names.extend(fields.iter().map(|field| field.lexeme.clone()));
+                    format!("({})", names.join(" "))
+                }));
+                format!("({})", parts.join(" "))
+            }
+            Stmt::Import {
+                keyword: _,
+                path,
+                alias: Some(alias),
+                names: _,
+            } => format!("(import \"{}\" as {})", path, alias.lexeme),
+            Stmt::Import {
+                keyword: _,
+                path,
+                alias: None,
+                names,
+            } => format!(
+                "(from \"{}\" import {})",
+                path,
+                names
+                    .iter()
+                    .map(|name| name.lexeme.clone())
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            ),
+            Stmt::Export {
+                keyword: _,
+                declaration,
+            } => format!("(export {})", declaration.to_string()),
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => match else_branch {
+                Some(else_branch) => format!(
+                    "(if {} {} {})",
+                    condition.to_string(),
+                    then_branch.to_string(),
+                    else_branch.to_string()
+                ),
+                None => format!("(if {} {})", condition.to_string(), then_branch.to_string()),
+            },
+            Stmt::While {
+                label,
+                condition,
+                body,
+                increment,
+            } => labelled(
+                label,
+                match increment {
+                    Some(increment) => format!(
+                        "(while {} {} {})",
+                        condition.to_string(),
+                        body.to_string(),
+                        increment.to_string()
+                    ),
+                    None => format!("(while {} {})", condition.to_string(), body.to_string()),
+                },
+            ),
+            Stmt::ForIn {
+                label,
+                keyword: _,
+                names,
+                paren,
+                iterable,
+                body,
+            } => {
+                let names = names
+                    .iter()
+                    .map(|name| name.lexeme.clone())
+                    .collect::<Vec<String>>()
+                    .join(" ");
+                labelled(
+                    label,
+                    format!(
+                        "(for {} {} {})",
+                        if paren.is_some() {
+                            format!("({})", names)
+                        } else {
+                            names
+                        },
+                        iterable.to_string(),
+                        body.to_string()
+                    ),
+                )
+            }
+            Stmt::Return { keyword: _, value } => format!("(return {})", value.to_string()),
+            Stmt::Yield { keyword: _, value } => format!("(yield {})", value.to_string()),
+            Stmt::Throw { keyword: _, value } => format!("(throw {})", value.to_string()),
+            Stmt::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                let block = |statements: &Vec<Stmt>| {
+                    statements
+                        .iter()
+                        .map(|stmt| stmt.to_string())
+                        .collect::<Vec<String>>()
+                        .join(" ")
+                };
+                let mut parts = vec![format!("try ({})", block(body))];
+                if let Some((name, handler)) = catch {
+                    parts.push(format!("(catch {} {})", name.lexeme, block(handler)));
+                }
+                if let Some(finally) = finally {
+                    parts.push(format!("(finally {})", block(finally)));
+                }
+                format!("({})", parts.join(" "))
+            }
+            Stmt::Switch {
+                keyword: _,
+                subject,
+                cases,
+                body,
+            } => {
+                let mut parts = vec![format!("switch {}", subject.to_string())];
+                for (i, case) in cases.iter().enumerate() {
+                    let end = match cases.get(i + 1) {
+                        Some(next) => next.start,
+                        None => body.len(),
+                    };
+                    let mut case_parts = if case.values.is_empty() {
+                        vec!["default".to_string()]
+                    } else {
+                        let mut values = vec!["case".to_string()];
+                        values.extend(case.values.iter().map(|value| value.to_string()));
+                        values
+                    };
+                    case_parts.extend(body[case.start..end].iter().map(|stmt| stmt.to_string()));
+                    parts.push(format!("({})", case_parts.join(" ")));
+                }
+                format!("({})", parts.join(" "))
+            }
+            Stmt::Break { keyword, label } | Stmt::Continue { keyword, label } => match label {
+                Some(label) => format!("({} {})", keyword.lexeme, label.lexeme),
+                None => format!("({})", keyword.lexeme),
+            },
+        }
+    }
+}
+
+fn labelled(label: &Option<Token>, stmt: String) -> String {
+    match label {
+        Some(label) => format!("(label {} {})", label.lexeme, stmt),
+        None => stmt,
+    }
+}
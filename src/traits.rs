@@ -0,0 +1,48 @@
+use crate::function::Function;
+use crate::scanner::Token;
+use std::{collections::HashMap, rc::Rc};
+
+/// A `trait` declaration: methods a class must define, and default methods
+/// it gets unless it defines its own.
+pub struct Trait {
+    pub name: String,
+    pub required: Vec<String>,
+    pub methods: HashMap<String, Rc<Function>>,
+}
+
+impl Trait {
+    /// Adds the default methods of `traits` that `methods` don't already
+    /// define, then checks that every required method is defined.
+    pub fn implement(
+        traits: &[Rc<Trait>],
+        methods: &mut HashMap<String, Rc<Function>>,
+        class_name: &Token,
+    ) -> Result<(), String> {
+        for implemented in traits {
+            for (name, method) in &implemented.methods {
+                methods
+                    .entry(name.clone())
+                    .or_insert_with(|| method.clone());
+            }
+        }
+
+        for implemented in traits {
+            for name in &implemented.required {
+                if !methods.contains_key(name) {
+                    return Err(format!(
+                        "Class {} is missing method '{}' required by trait {}",
+                        class_name.lexeme, name, implemented.name
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for Trait {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "<trait {}>", self.name)
+    }
+}